@@ -0,0 +1,214 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use hashbrown::HashMap;
+use indicatif::{ParallelProgressIterator, ProgressBar, ProgressStyle};
+use rayon::prelude::*;
+use xfbin::{read_xfbin, write_xfbin};
+
+use crate::error::StrmConvError;
+use crate::{convert_strm_xfbin, ConvertOptions};
+
+const STRM_XFBIN_SUFFIX: &str = ".strm.xfbin";
+
+/// A strm .xfbin found in a batch directory, with its frame settings .xml if there is one.
+#[derive(Debug, Clone)]
+pub struct BatchJob {
+    /// Chunk name of the strm, the file name up to the first '.'.
+    pub chunk_name: String,
+    pub xfbin_path: PathBuf,
+    pub xml_path: Option<PathBuf>,
+    /// Directory of the strm relative to the batch directory, recreated inside the output
+    /// directory so strms of different subdirectories don't overwrite each other.
+    pub relative_dir: PathBuf,
+}
+
+/// The outcome of converting one [`BatchJob`].
+#[derive(Debug)]
+pub struct BatchResult {
+    pub job: BatchJob,
+    /// The path of the converted xfbin, or why the conversion failed.
+    pub result: Result<PathBuf, StrmConvError>,
+    pub elapsed: Duration,
+}
+
+/// Finds every `*.strm.xfbin` in a directory and pairs it with the `*.xml` of the same chunk name.
+///
+/// Only an .xml in the same directory as the strm is used, so strms of different subdirectories
+/// that share a chunk name each keep their own frame settings.
+pub fn find_batch_jobs(dir: &Path, recursive: bool) -> Result<Vec<BatchJob>, StrmConvError> {
+    let mut xfbin_paths: Vec<PathBuf> = Vec::new();
+    let mut xml_paths: Vec<PathBuf> = Vec::new();
+
+    collect_files(dir, recursive, &mut xfbin_paths, &mut xml_paths)?;
+
+    let mut xmls_by_name: HashMap<(Option<PathBuf>, String), PathBuf> = HashMap::new();
+    for xml_path in xml_paths {
+        let dir = xml_path.parent().map(Path::to_path_buf);
+
+        xmls_by_name
+            .entry((dir, chunk_name_of(&xml_path)))
+            .or_insert(xml_path);
+    }
+
+    let mut jobs: Vec<BatchJob> = xfbin_paths
+        .into_iter()
+        .map(|xfbin_path| {
+            let chunk_name = chunk_name_of(&xfbin_path);
+
+            let xfbin_dir = xfbin_path.parent().map(Path::to_path_buf);
+            let xml_path = xmls_by_name
+                .get(&(xfbin_dir.clone(), chunk_name.clone()))
+                .cloned();
+
+            let relative_dir = xfbin_dir
+                .as_deref()
+                .and_then(|xfbin_dir| xfbin_dir.strip_prefix(dir).ok())
+                .map(Path::to_path_buf)
+                .unwrap_or_default();
+
+            BatchJob {
+                chunk_name,
+                xfbin_path,
+                xml_path,
+                relative_dir,
+            }
+        })
+        .collect();
+
+    jobs.sort_by(|a, b| a.xfbin_path.cmp(&b.xfbin_path));
+
+    Ok(jobs)
+}
+
+/// Converts every job in parallel and returns the results in the order of the jobs.
+///
+/// Each converted xfbin is written to `output_dir`, in the job's subdirectory of the batch
+/// directory, or next to its strm .xfbin when `None`. A failing job doesn't stop the others.
+pub fn run_batch(
+    jobs: &[BatchJob],
+    options: &ConvertOptions,
+    output_dir: Option<&Path>,
+) -> Vec<BatchResult> {
+    let pb = if options.quiet {
+        ProgressBar::hidden()
+    } else {
+        let pb = ProgressBar::new(jobs.len() as u64);
+        pb.set_style(
+            ProgressStyle::with_template(
+                "converting files...   {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}",
+            )
+            .unwrap_or_else(|_| ProgressStyle::default_bar())
+            .progress_chars("||-"),
+        );
+        pb
+    };
+
+    // Progress bars of the single conversions would overlap, so they are always hidden here
+    let job_options = ConvertOptions {
+        chunk_name: None,
+        quiet: true,
+        ..options.clone()
+    };
+
+    let results = jobs
+        .par_iter()
+        .progress_with(pb.clone())
+        .map(|job| {
+            let time = Instant::now();
+            let result = convert_job(job, &job_options, output_dir);
+
+            BatchResult {
+                job: job.clone(),
+                result,
+                elapsed: time.elapsed(),
+            }
+        })
+        .collect();
+
+    pb.finish_with_message("done");
+
+    results
+}
+
+/// Reads, converts and writes a single strm .xfbin.
+fn convert_job(
+    job: &BatchJob,
+    options: &ConvertOptions,
+    output_dir: Option<&Path>,
+) -> Result<PathBuf, StrmConvError> {
+    let xfbin = read_xfbin(&job.xfbin_path).map_err(|err| StrmConvError::Xfbin(err.to_string()))?;
+
+    let frame_settings_xml = match &job.xml_path {
+        Some(xml_path) => Some(fs::read_to_string(xml_path)?),
+        None => None,
+    };
+
+    let options = ConvertOptions {
+        chunk_name: Some(job.chunk_name.clone()),
+        frame_settings_xml,
+        ..options.clone()
+    };
+
+    let output = convert_strm_xfbin(&xfbin, &options)?;
+
+    let converted_filename = output.chunk_name.clone() + ".anm.xfbin";
+    let converted_path = match output_dir {
+        Some(output_dir) => {
+            let output_dir = output_dir.join(&job.relative_dir);
+            fs::create_dir_all(&output_dir)?;
+
+            output_dir.join(converted_filename)
+        }
+        None => job.xfbin_path.with_file_name(converted_filename),
+    };
+
+    write_xfbin(output.xfbin, &converted_path)
+        .map_err(|err| StrmConvError::Xfbin(err.to_string()))?;
+
+    Ok(converted_path)
+}
+
+/// Collects the strm .xfbin and .xml files of a directory.
+fn collect_files(
+    dir: &Path,
+    recursive: bool,
+    xfbin_paths: &mut Vec<PathBuf>,
+    xml_paths: &mut Vec<PathBuf>,
+) -> Result<(), StrmConvError> {
+    for dir_entry in fs::read_dir(dir)? {
+        let path = dir_entry?.path();
+
+        if path.is_dir() {
+            if recursive {
+                collect_files(&path, recursive, xfbin_paths, xml_paths)?;
+            }
+            continue;
+        }
+
+        let file_name = path
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        if file_name.ends_with(STRM_XFBIN_SUFFIX) {
+            xfbin_paths.push(path);
+        } else if file_name.ends_with(".xml") {
+            xml_paths.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the file name of a path up to the first '.'.
+fn chunk_name_of(path: &Path) -> String {
+    path.file_name()
+        .map(|file_name| file_name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+        .split('.')
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
@@ -0,0 +1,294 @@
+use std::fmt::Write;
+
+use hashbrown::HashMap;
+use xfbin::nucc::NuccStructReference;
+use xfbin::nucc_chunk::nucc_chunk_anmstrmframe::Entry;
+use xfbin::nucc_chunk::nucc_helper::{Vector3, Vector4};
+use xfbin::Xfbin;
+
+use crate::converter::{build_entries_from_frames, find_anmstrm};
+use crate::error::StrmConvError;
+use crate::math::quaternion_to_euler;
+use crate::sample::FRAME_TICKS;
+
+/// Options for [`export_bvh`].
+#[derive(Debug, Clone)]
+pub struct BvhOptions {
+    /// Chunk name of the ANMSTRM to export, the first ANMSTRM when `None`.
+    pub chunk_name: Option<String>,
+    /// Number of keyframe ticks in one frame.
+    pub frame_ticks: i32,
+    /// Frames per second the animation is played back at, written as the `Frame Time`.
+    ///
+    /// The frames aren't resampled: every `frame_ticks` ticks of the ANMSTRM is one BVH frame,
+    /// so this has to be the rate the ANMSTRM was made for at that `frame_ticks`, 30 fps for
+    /// the default of 100 ticks.
+    pub fps: f32,
+}
+
+impl Default for BvhOptions {
+    fn default() -> Self {
+        Self {
+            chunk_name: None,
+            frame_ticks: FRAME_TICKS,
+            fps: 30.0,
+        }
+    }
+}
+
+/// The BVH of one clump.
+#[derive(Debug, Clone)]
+pub struct BvhOutput {
+    /// Chunk name of the clump, used for the output file name.
+    pub clump_name: String,
+    pub bvh: String,
+}
+
+/// Frame, location and rotation of every stored frame of every bone, by clump and coord index.
+type BoneFrames = HashMap<(i16, u16), Vec<(i32, Vector3, Vector4)>>;
+
+/// A joint of a clump's skeleton.
+struct Joint {
+    name: String,
+    coord_index: Option<u16>,
+    offset: Vector3,
+    children: Vec<usize>,
+}
+
+/// Exports the bones of every clump of an ANMSTRM to BVH, one file per clump.
+///
+/// The hierarchy follows the coord parents inside the clump. Every joint is offset from its
+/// parent by its location in the first frame and is animated with `Zrotation Yrotation
+/// Xrotation` channels, the root also gets position channels. Only the root moves: the other
+/// joints keep the first-frame location as their `OFFSET`, so bones whose location is
+/// animated, like stretchy or detached ones, lose that movement. A clump with several top
+/// level bones gets a root joint named after the clump. Frames the ANMSTRM doesn't store
+/// hold the previous frame's values.
+pub fn export_bvh(xfbin: &Xfbin, options: &BvhOptions) -> Result<Vec<BvhOutput>, StrmConvError> {
+    let chunk_name = match &options.chunk_name {
+        Some(chunk_name) => chunk_name.clone(),
+        None => crate::first_anmstrm_name(xfbin).ok_or(StrmConvError::NoAnmStrmChunk)?,
+    };
+
+    let (page_index, anmstrm, anmstrmframes) = find_anmstrm(xfbin, &chunk_name)?;
+    let struct_references = &xfbin.pages[page_index].struct_references;
+    let frame_ticks = options.frame_ticks.max(1);
+
+    let mut bone_frames = BoneFrames::new();
+    let mut frame_count = 0;
+
+    for (tick, entry) in build_entries_from_frames(anmstrmframes, &mut Vec::new(), true)
+        .into_iter()
+        .flatten()
+    {
+        if let Entry::Bone(bone) = entry.entry_data {
+            let frame = tick / frame_ticks;
+            frame_count = frame_count.max(frame + 1);
+
+            bone_frames
+                .entry((entry.coord.clump_index, entry.coord.coord_index))
+                .or_default()
+                .push((frame, bone.location, bone.rotation));
+        }
+    }
+
+    let mut outputs = Vec::new();
+
+    for (clump_index, clump) in anmstrm.clumps.iter().enumerate() {
+        let clump_index = clump_index as i16;
+        let clump_name = reference_name(struct_references, clump.clump_index)
+            .unwrap_or_else(|| format!("clump_{}", clump_index));
+
+        let mut coord_indices = bone_frames
+            .keys()
+            .filter(|(bone_clump_index, _)| *bone_clump_index == clump_index)
+            .map(|(_, coord_index)| *coord_index)
+            .collect::<Vec<_>>();
+        if coord_indices.is_empty() {
+            continue;
+        }
+
+        // Parents without an animation of their own are still needed to keep the hierarchy
+        for coord_parent in &anmstrm.coord_parents {
+            if coord_parent.parent.clump_index == clump_index
+                && coord_parent.child.clump_index == clump_index
+                && coord_indices.contains(&coord_parent.child.coord_index)
+                && !coord_indices.contains(&coord_parent.parent.coord_index)
+            {
+                coord_indices.push(coord_parent.parent.coord_index);
+            }
+        }
+        coord_indices.sort_unstable();
+
+        let mut joints = coord_indices
+            .iter()
+            .map(|&coord_index| Joint {
+                name: clump
+                    .bone_material_indices
+                    .get(coord_index as usize)
+                    .and_then(|&index| reference_name(struct_references, index))
+                    .unwrap_or_else(|| format!("coord_{}", coord_index)),
+                coord_index: Some(coord_index),
+                offset: bone_frames
+                    .get(&(clump_index, coord_index))
+                    .and_then(|frames| frames.first())
+                    .map(|(_, location, _)| location.clone())
+                    .unwrap_or_default(),
+                children: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+
+        let joint_index = |coord_index: u16| coord_indices.binary_search(&coord_index).ok();
+
+        let mut has_parent = vec![false; joints.len()];
+        for coord_parent in &anmstrm.coord_parents {
+            if coord_parent.parent.clump_index != clump_index
+                || coord_parent.child.clump_index != clump_index
+            {
+                continue;
+            }
+
+            if let (Some(parent), Some(child)) = (
+                joint_index(coord_parent.parent.coord_index),
+                joint_index(coord_parent.child.coord_index),
+            ) {
+                if parent != child && !has_parent[child] {
+                    has_parent[child] = true;
+                    joints[parent].children.push(child);
+                }
+            }
+        }
+
+        let top_level = (0..joints.len())
+            .filter(|&joint| !has_parent[joint])
+            .collect::<Vec<_>>();
+
+        // A cycle in the coord parents leaves no top level bone, those bones can't be written
+        let root = match top_level.as_slice() {
+            [] => continue,
+            [root] => *root,
+            _ => {
+                joints.push(Joint {
+                    name: clump_name.clone(),
+                    coord_index: None,
+                    offset: Vector3::default(),
+                    children: top_level,
+                });
+                joints.len() - 1
+            }
+        };
+
+        let mut order = Vec::new();
+        let mut bvh = String::from("HIERARCHY\n");
+        write_joint(&mut bvh, &joints, root, 0, &mut order);
+
+        let _ = writeln!(bvh, "MOTION");
+        let _ = writeln!(bvh, "Frames: {}", frame_count);
+        let _ = writeln!(bvh, "Frame Time: {:.6}", 1.0 / options.fps);
+
+        // Index of the stored frame each joint is at, values are held until the next one
+        let mut cursors = vec![0usize; joints.len()];
+
+        for frame in 0..frame_count {
+            let mut values = Vec::new();
+
+            for &joint in &order {
+                let (location, rotation) = match joints[joint]
+                    .coord_index
+                    .and_then(|coord_index| bone_frames.get(&(clump_index, coord_index)))
+                {
+                    Some(frames) => {
+                        while cursors[joint] + 1 < frames.len()
+                            && frames[cursors[joint] + 1].0 <= frame
+                        {
+                            cursors[joint] += 1;
+                        }
+
+                        let (_, location, rotation) = &frames[cursors[joint]];
+                        (location.clone(), quaternion_to_euler(rotation))
+                    }
+                    None => (Vector3::default(), Vector3::default()),
+                };
+
+                if joint == root {
+                    values.extend([location.x, location.y, location.z]);
+                }
+                values.extend([rotation.z, rotation.y, rotation.x]);
+            }
+
+            let line = values
+                .iter()
+                .map(|value| format!("{:.6}", value))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let _ = writeln!(bvh, "{}", line);
+        }
+
+        outputs.push(BvhOutput { clump_name, bvh });
+    }
+
+    Ok(outputs)
+}
+
+/// Writes a joint and its children to the hierarchy, adding them to `order` in channel order.
+fn write_joint(
+    bvh: &mut String,
+    joints: &[Joint],
+    joint: usize,
+    depth: usize,
+    order: &mut Vec<usize>,
+) {
+    let indent = "\t".repeat(depth);
+    let name = joint_name(&joints[joint].name);
+    let offset = &joints[joint].offset;
+
+    order.push(joint);
+
+    if depth == 0 {
+        let _ = writeln!(bvh, "ROOT {}", name);
+    } else {
+        let _ = writeln!(bvh, "{}JOINT {}", indent, name);
+    }
+    let _ = writeln!(bvh, "{}{{", indent);
+
+    if depth == 0 {
+        // The root's position channels place it, so it has no offset of its own
+        let _ = writeln!(bvh, "{}\tOFFSET 0.000000 0.000000 0.000000", indent);
+        let _ = writeln!(
+            bvh,
+            "{}\tCHANNELS 6 Xposition Yposition Zposition Zrotation Yrotation Xrotation",
+            indent
+        );
+    } else {
+        let _ = writeln!(
+            bvh,
+            "{}\tOFFSET {:.6} {:.6} {:.6}",
+            indent, offset.x, offset.y, offset.z
+        );
+        let _ = writeln!(bvh, "{}\tCHANNELS 3 Zrotation Yrotation Xrotation", indent);
+    }
+
+    if joints[joint].children.is_empty() {
+        let _ = writeln!(bvh, "{}\tEnd Site", indent);
+        let _ = writeln!(bvh, "{}\t{{", indent);
+        let _ = writeln!(bvh, "{}\t\tOFFSET 0.000000 0.000000 0.000000", indent);
+        let _ = writeln!(bvh, "{}\t}}", indent);
+    } else {
+        for &child in &joints[joint].children {
+            write_joint(bvh, joints, child, depth + 1, order);
+        }
+    }
+
+    let _ = writeln!(bvh, "{}}}", indent);
+}
+
+fn reference_name(struct_references: &[NuccStructReference], index: u32) -> Option<String> {
+    struct_references
+        .get(index as usize)
+        .map(|reference| reference.struct_info.chunk_name.clone())
+}
+
+/// A joint name without whitespace, which BVH readers split names on.
+fn joint_name(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join("_")
+}
@@ -0,0 +1,329 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use strmconv::looping::LoopMode;
+use strmconv::optimize::KeyframeReduction;
+use strmconv::split::SplitGroup;
+use strmconv::trim::FrameRange;
+use strmconv::DmgSplit;
+
+/// .anmstrm converter for the Ultimate Ninja STORM series
+///
+/// Converts a strm .xfbin (e.g. `d30_10.strm.xfbin`) and, if given, its frame settings
+/// .xml (e.g. `d30_10.xml`) into an anm .xfbin. Both files can be given in any order,
+/// so dragging them onto the exe keeps working. Without an .xml the FCV page is skipped.
+#[derive(Debug, Parser)]
+#[command(name = "strmconv", version, about, long_about)]
+pub struct Args {
+    /// The strm .xfbin to convert and, optionally, its frame settings .xml
+    #[arg(
+        value_name = "FILES",
+        required_unless_present_any = ["batch", "import"],
+        conflicts_with_all = ["batch", "import"],
+        num_args = 1..=2
+    )]
+    pub inputs: Vec<PathBuf>,
+
+    /// Convert every *.strm.xfbin in a directory, paired with the *.xml of the same name
+    #[arg(short, long, value_name = "DIR", conflicts_with_all = ["output", "name"])]
+    pub batch: Option<PathBuf>,
+
+    /// Also convert the files in the subdirectories of the batch directory
+    #[arg(short, long, requires = "batch")]
+    pub recursive: bool,
+
+    /// Path of the converted .xfbin [default: <NAME>.anm.xfbin, <NAME>.strm.xfbin with --to-strm]
+    #[arg(short, long, value_name = "FILE", conflicts_with = "output_dir")]
+    pub output: Option<PathBuf>,
+
+    /// Directory to write the converted .xfbin to [default for --batch: next to each strm]
+    #[arg(short = 'd', long, value_name = "DIR")]
+    pub output_dir: Option<PathBuf>,
+
+    /// Chunk name of the ANMSTRM to convert [default: the first ANMSTRM in the file]
+    #[arg(short, long)]
+    pub name: Option<String>,
+
+    /// Convert every ANMSTRM in the file to its own anm .xfbin, each with only its own frames
+    #[arg(long, conflicts_with_all = ["batch", "name", "output", "to_strm", "split", "merge"])]
+    pub all_streams: bool,
+
+    /// Don't build the DMG anm page (with --to-strm: don't merge the DMG anm back)
+    #[arg(long)]
+    pub no_dmg: bool,
+
+    /// Remove bone location, rotation and scale keys that can be interpolated from their
+    /// neighbours, and collapse bone toggles that don't change to a single value
+    #[arg(long)]
+    pub reduce: bool,
+
+    /// Location tolerance of --reduce
+    #[arg(
+        long,
+        value_name = "DISTANCE",
+        default_value_t = 0.01,
+        requires = "reduce"
+    )]
+    pub translation_tolerance: f32,
+
+    /// Rotation tolerance of --reduce, in degrees. A rotation is only reduced when its keys as
+    /// float quaternions take less space than one short quaternion per frame
+    #[arg(
+        long,
+        value_name = "DEGREES",
+        default_value_t = 0.1,
+        requires = "reduce"
+    )]
+    pub rotation_tolerance: f32,
+
+    /// Scale tolerance of --reduce
+    #[arg(
+        long,
+        value_name = "DISTANCE",
+        default_value_t = 0.001,
+        requires = "reduce"
+    )]
+    pub scale_tolerance: f32,
+
+    /// Write curves that hold the same value on every frame with a single key
+    #[arg(long)]
+    pub collapse_constant: bool,
+
+    /// Number of keyframe ticks in one frame, for games that use a different frame unit
+    #[arg(
+        long,
+        value_name = "TICKS",
+        default_value_t = 100,
+        value_parser = clap::value_parser!(u32).range(1..),
+        conflicts_with = "to_strm"
+    )]
+    pub frame_ticks: u32,
+
+    /// Stretch the timing of the animation by this factor, 2 plays it at half speed
+    #[arg(
+        long,
+        value_name = "FACTOR",
+        value_parser = parse_positive,
+        conflicts_with_all = ["to_strm", "fps"]
+    )]
+    pub time_scale: Option<f32>,
+
+    /// Resample the animation from one frame rate to another, e.g. 30:60
+    #[arg(long, value_name = "FROM:TO", value_parser = parse_fps, conflicts_with = "to_strm")]
+    pub fps: Option<(f32, f32)>,
+
+    /// Only convert these frames, e.g. 120..480 (end excluded) or 120..=480 (end included)
+    #[arg(long, value_name = "START..END", conflicts_with = "to_strm")]
+    pub frames: Option<FrameRange>,
+
+    /// Make the anm loop, even if the ANMSTRM doesn't
+    #[arg(long = "loop", conflicts_with_all = ["no_loop", "to_strm"])]
+    pub force_loop: bool,
+
+    /// Make the anm play once, even if the ANMSTRM loops
+    #[arg(long, conflicts_with = "to_strm")]
+    pub no_loop: bool,
+
+    /// Use the clump with this chunk name for the DMG anm page (repeatable)
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["no_dmg", "dmg_filepath", "dmg_index"])]
+    pub dmg_clump: Vec<String>,
+
+    /// Use the clumps whose filepath contains this pattern for the DMG anm page
+    #[arg(long, value_name = "PATTERN", conflicts_with_all = ["no_dmg", "dmg_index"])]
+    pub dmg_filepath: Option<String>,
+
+    /// Use the clump at this index for the DMG anm page (repeatable)
+    #[arg(long, value_name = "INDEX", conflicts_with = "no_dmg")]
+    pub dmg_index: Vec<usize>,
+
+    /// Convert an anm .xfbin back into a strm .xfbin
+    #[arg(long, conflicts_with = "batch")]
+    pub to_strm: bool,
+
+    /// Split an anm .xfbin, writing the clumps of each group to its own anm .xfbin (repeatable)
+    ///
+    /// Groups are given as NAME=CLUMP[,CLUMP...], or as just CLUMP to name the new anm after
+    /// the clump.
+    #[arg(
+        long,
+        value_name = "GROUP",
+        value_parser = parse_split_group,
+        conflicts_with_all = ["batch", "to_strm", "output"]
+    )]
+    pub split: Vec<SplitGroup>,
+
+    /// Keep the cameras, lights and other entries that don't belong to a clump in every --split anm
+    #[arg(long, requires = "split")]
+    pub split_keep_other: bool,
+
+    /// Merge the anm or strm .xfbin into the input, writing every ANM to one anm .xfbin (repeatable)
+    ///
+    /// The main and _dmg anm are both merged, --name sets the chunk name of the merged anm.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["batch", "to_strm", "split"])]
+    pub merge: Vec<PathBuf>,
+
+    /// Dump the ANMSTRMs and ANMs of the xfbin to JSON instead of converting it
+    ///
+    /// The JSON is written to --output, or printed when no output is given.
+    #[arg(long, conflicts_with_all = ["batch", "to_strm", "split", "merge", "all_streams"])]
+    pub dump: bool,
+
+    /// Leave the per-frame values of the ANMSTRMs out of the --dump
+    #[arg(long, requires = "dump")]
+    pub dump_headers_only: bool,
+
+    /// Write the bone transforms of every frame of the ANMSTRM to <NAME>.csv instead of converting
+    #[arg(long, conflicts_with_all = ["batch", "dump", "to_strm", "split", "merge", "all_streams"])]
+    pub csv: bool,
+
+    /// Write the bones of every clump of the ANMSTRM to <NAME>_<CLUMP>.bvh instead of converting
+    #[arg(long, conflicts_with_all = ["batch", "output", "dump", "csv", "to_strm", "split", "merge", "all_streams"])]
+    pub bvh: bool,
+
+    /// Build an anm .xfbin from a JSON description, like the ANMs of a --dump, instead of converting
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["batch", "dump", "to_strm", "split", "merge", "all_streams"])]
+    pub import: Option<PathBuf>,
+
+    /// Also export the converted anm to glTF 2.0, as a .gltf with its .bin next to the anm .xfbin
+    #[arg(long, conflicts_with_all = ["batch", "to_strm", "split", "merge", "glb"])]
+    pub gltf: bool,
+
+    /// Also export the converted anm to a binary glTF 2.0 .glb next to the anm .xfbin
+    #[arg(long, conflicts_with_all = ["batch", "to_strm", "split", "merge"])]
+    pub glb: bool,
+
+    /// Don't build the FCV page from the frame settings .xml
+    #[arg(long)]
+    pub no_fcv: bool,
+
+    /// Only print errors
+    #[arg(short, long)]
+    pub quiet: bool,
+}
+
+impl Args {
+    /// Returns the keyframe reduction tolerances if --reduce was given.
+    pub fn keyframe_reduction(&self) -> Option<KeyframeReduction> {
+        self.reduce.then_some(KeyframeReduction {
+            translation_tolerance: self.translation_tolerance,
+            rotation_tolerance: self.rotation_tolerance,
+            scale_tolerance: self.scale_tolerance,
+        })
+    }
+
+    /// Returns the factor the timing of the animation is stretched by.
+    pub fn time_scale(&self) -> f32 {
+        match (self.time_scale, self.fps) {
+            (Some(time_scale), _) => time_scale,
+            (None, Some((from, to))) => to / from,
+            (None, None) => 1.0,
+        }
+    }
+
+    /// Returns whether the converted anm should loop.
+    pub fn loop_mode(&self) -> LoopMode {
+        if self.force_loop {
+            LoopMode::Force
+        } else if self.no_loop {
+            LoopMode::Clear
+        } else {
+            LoopMode::Keep
+        }
+    }
+
+    /// Returns how the DMG clumps should be chosen.
+    pub fn dmg_split(&self) -> DmgSplit {
+        if self.no_dmg {
+            DmgSplit::Disabled
+        } else if !self.dmg_clump.is_empty() {
+            DmgSplit::ChunkNames(self.dmg_clump.clone())
+        } else if let Some(pattern) = &self.dmg_filepath {
+            DmgSplit::FilepathPattern(pattern.clone())
+        } else if !self.dmg_index.is_empty() {
+            DmgSplit::Indices(self.dmg_index.clone())
+        } else {
+            DmgSplit::Heuristic
+        }
+    }
+
+    /// Splits the positional inputs into the strm .xfbin and the optional .xml.
+    pub fn input_files(&self) -> Result<(PathBuf, Option<PathBuf>), String> {
+        let mut xfbin_path: Option<PathBuf> = None;
+        let mut xml_path: Option<PathBuf> = None;
+
+        for input in &self.inputs {
+            let extension = input
+                .extension()
+                .map(|extension| extension.to_string_lossy().to_lowercase());
+
+            let slot = match extension.as_deref() {
+                Some("xfbin") => &mut xfbin_path,
+                Some("xml") => &mut xml_path,
+                _ => {
+                    return Err(format!(
+                        "'{}' is neither an .xfbin nor an .xml file",
+                        input.display()
+                    ))
+                }
+            };
+
+            if let Some(previous) = slot {
+                return Err(format!(
+                    "got both '{}' and '{}', expected only one of each file type",
+                    previous.display(),
+                    input.display()
+                ));
+            }
+
+            *slot = Some(input.clone());
+        }
+
+        match xfbin_path {
+            Some(xfbin_path) => Ok((xfbin_path, xml_path)),
+            None => Err("no strm .xfbin file was given".to_string()),
+        }
+    }
+}
+
+/// Parses a --split group, NAME=CLUMP[,CLUMP...] or CLUMP.
+fn parse_split_group(value: &str) -> Result<SplitGroup, String> {
+    let (chunk_name, clump_names) = match value.split_once('=') {
+        Some((chunk_name, clump_names)) => (chunk_name, clump_names),
+        None => (value, value),
+    };
+
+    let clump_names: Vec<String> = clump_names
+        .split(',')
+        .map(str::trim)
+        .filter(|clump_name| !clump_name.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    if chunk_name.trim().is_empty() || clump_names.is_empty() {
+        return Err("expected NAME=CLUMP[,CLUMP...] or CLUMP".to_string());
+    }
+
+    Ok(SplitGroup {
+        chunk_name: chunk_name.trim().to_string(),
+        clump_names,
+    })
+}
+
+/// Parses the --fps frame rates, FROM:TO.
+fn parse_fps(value: &str) -> Result<(f32, f32), String> {
+    match value.split_once(':') {
+        Some((from, to)) => Ok((parse_positive(from)?, parse_positive(to)?)),
+        None => Err("expected FROM:TO, e.g. 30:60".to_string()),
+    }
+}
+
+/// Parses a number that has to be greater than zero.
+fn parse_positive(value: &str) -> Result<f32, String> {
+    value
+        .trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|number| *number > 0.0 && number.is_finite())
+        .ok_or_else(|| format!("'{}' is not a positive number", value))
+}
@@ -1,13 +1,13 @@
+use std::mem::{self, Discriminant};
+
 use hashbrown::HashMap;
 use indicatif::{ProgressBar, ProgressStyle};
-use rayon::prelude::*;
-use std::error::Error;
 use xfbin::Xfbin;
 
 //use crate::structure::anm::{NuccAnm, AnmEntry, AnmEntryFormat, AnmCurveFormat, Curve, CurveHeader, AnmClump};
 
 use xfbin::nucc_chunk::nucc_chunk_anm::{
-    AnmClump, AnmCurveFormat, AnmEntry, AnmEntryFormat, Curve, CurveHeader,
+    AnmClump, AnmCoord, AnmCurveFormat, AnmEntry, AnmEntryFormat, Curve, CurveHeader,
 };
 use xfbin::nucc_chunk::nucc_chunk_anmstrmframe::{AnmStrmEntry, Entry};
 use xfbin::nucc_chunk::{nucc_helper::*, NuccChunkType};
@@ -17,9 +17,52 @@ use xfbin::nucc::nucc_anmstrm::NuccAnmStrm;
 use xfbin::nucc::nucc_anmstrmframe::NuccAnmStrmFrame;
 use xfbin::nucc::{NuccStructInfo, NuccStructReference};
 
+use crate::error::StrmConvError;
+use crate::looping::{close_loop, LoopPop};
+use crate::math::{normalize_quaternion, quaternion_dot};
+use crate::optimize::{
+    collapse_constant_curves, reduce_bone_curves, KeyframeReduction, ReductionReport,
+};
+use crate::remap::{retain_referenced_struct_infos, ClumpRemap};
+use crate::resample::{resample_entry, resample_frame_count};
+use crate::{ConvertOptions, DmgSplit};
+
 const QUAT_COMPRESS: f32 = 0x4000 as f32;
 const RGB_CONVERT: f32 = 255.0;
 
+/// Details about a conversion that don't stop it.
+#[derive(Debug, Clone, Default)]
+pub struct ConversionReport {
+    /// What the keyframe reduction and constant curve collapsing saved.
+    pub reduction: ReductionReport,
+    /// Entries of a type that can't be converted yet, ordered by coord.
+    pub skipped_entries: Vec<SkippedEntry>,
+    /// Curves of a looped animation whose last frame doesn't match the first.
+    pub loop_pops: Vec<LoopPop>,
+}
+
+/// How the curves of every entry are processed after they're gathered from the frames.
+struct CurveSettings<'a> {
+    frame_ticks: i32,
+    time_scale: Option<f32>,
+    close_loop: bool,
+    keyframe_reduction: Option<&'a KeyframeReduction>,
+    collapse_constants: bool,
+}
+
+/// An ANMSTRM entry that was dropped because xfbin couldn't parse its data.
+///
+/// Every entry type xfbin knows is converted, so these are the entries of an unknown entry
+/// format, whose curve layout isn't known.
+#[derive(Debug, Clone)]
+pub struct SkippedEntry {
+    /// Name of the entry type.
+    pub kind: String,
+    pub coord: AnmCoord,
+    /// Number of frames the entry was dropped from.
+    pub frames: usize,
+}
+
 /// Converts ANMSTRM data into a vector of ANM data (ANM and DMG ANM)
 pub fn convert_anmstrm(
     xfbin: &Xfbin,
@@ -27,21 +70,141 @@ pub fn convert_anmstrm(
     dmg_struct_info: &NuccStructInfo,
     struct_references: &mut Vec<NuccStructReference>,
     struct_infos: &mut Vec<NuccStructInfo>,
-) -> Result<Vec<NuccAnm>, Box<dyn Error>> {
-    let anmstrm: &NuccAnmStrm = xfbin
+    report: &mut ConversionReport,
+    options: &ConvertOptions,
+) -> Result<Vec<NuccAnm>, StrmConvError> {
+    let quiet = options.quiet;
+
+    // An xfbin can hold several streams, so only the ANMSTRM being converted and its own frames are used
+    let (_, anmstrm, anmstrmframes) = find_anmstrm(xfbin, &anm_struct_info.chunk_name)?;
+
+    let frame_ticks = options.frame_ticks.max(1) as i32;
+
+    // Only the frames inside the range are converted, moved so the range starts at tick 0
+    let (anmstrmframes, trimmed_frame_count) = match &options.frame_range {
+        Some(range) => {
+            let anmstrmframes = anmstrmframes
+                .into_iter()
+                .filter(|anmstrmframe| range.contains(anmstrmframe.frame / frame_ticks as u32))
+                .collect::<Vec<_>>();
+
+            let last_frame = anmstrmframes
+                .iter()
+                .map(|anmstrmframe| anmstrmframe.frame / frame_ticks as u32)
+                .max()
+                .ok_or(StrmConvError::EmptyFrameRange { range: *range })?;
+
+            (
+                anmstrmframes,
+                Some((last_frame - range.start) * frame_ticks as u32),
+            )
+        }
+        None => (anmstrmframes, None),
+    };
+
+    let mut anmstrm_entries =
+        build_anmstrm_entries_map(anmstrmframes, &mut report.skipped_entries, quiet)?;
+
+    if let Some(range) = &options.frame_range {
+        let start_tick = range.start as i32 * frame_ticks;
+
+        for (tick, _) in anmstrm_entries.values_mut().flatten() {
+            *tick -= start_tick;
+        }
+    }
+
+    let time_scale =
+        (options.time_scale > 0.0 && options.time_scale != 1.0).then_some(options.time_scale);
+
+    let is_looped = options.loop_mode.is_looped(anmstrm.is_looped);
+
+    let settings = CurveSettings {
+        frame_ticks,
+        time_scale,
+        close_loop: is_looped,
+        keyframe_reduction: options.keyframe_reduction.as_ref(),
+        collapse_constants: options.collapse_constant_curves,
+    };
+
+    let anm_entries = convert_entries(anmstrm_entries, &settings, report, quiet);
+
+    if !quiet {
+        println!("building anm files...");
+    }
+    let mut anm = build_anm(anmstrm, anm_entries, anm_struct_info, frame_ticks)?;
+    anm.is_looped = is_looped;
+    if let Some(frame_count) = trimmed_frame_count {
+        anm.frame_count = frame_count;
+    }
+    if let Some(time_scale) = time_scale {
+        anm.frame_count = resample_frame_count(anm.frame_count, time_scale, frame_ticks);
+    }
+
+    let (dmg_clump_indices, removed_clump_indices) =
+        match select_dmg_clumps(&anm, struct_references, &options.dmg_split)? {
+            Some(selection) => selection,
+            None => return Ok(vec![anm]),
+        };
+
+    let dmg_anm = build_dmg_anm(
+        &mut anm,
+        dmg_struct_info,
+        struct_references,
+        struct_infos,
+        &dmg_clump_indices,
+        &removed_clump_indices,
+    ); // Consumes the original anm to create the anm from the dmg clump and mutates the original anm
+
+    Ok(vec![anm, dmg_anm])
+}
+
+/// Creates a progress bar with the given template, or a hidden one when `quiet` is set.
+fn progress_bar(len: u64, template: &str, quiet: bool) -> ProgressBar {
+    if quiet {
+        return ProgressBar::hidden();
+    }
+
+    let pb = ProgressBar::new(len);
+    pb.set_style(
+        ProgressStyle::with_template(template)
+            .unwrap_or_else(|_| ProgressStyle::default_bar())
+            .progress_chars("||-"),
+    );
+
+    pb
+}
+
+/// Finds the ANMSTRM with the given chunk name, the index of its page and its own frames.
+pub(crate) fn find_anmstrm<'a>(
+    xfbin: &'a Xfbin,
+    chunk_name: &str,
+) -> Result<(usize, &'a NuccAnmStrm, Vec<&'a NuccAnmStrmFrame>), StrmConvError> {
+    let (page_index, anmstrm) = xfbin
         .pages
         .iter()
-        .flat_map(|page| {
-            page.structs.iter().filter_map(|nucc_struct| {
+        .enumerate()
+        .flat_map(|(page_index, page)| {
+            page.structs.iter().filter_map(move |nucc_struct| {
                 if let NuccChunkType::NuccChunkAnmStrm = nucc_struct.chunk_type() {
-                    Some(nucc_struct.downcast_ref::<NuccAnmStrm>().unwrap())
+                    Some(
+                        nucc_struct
+                            .downcast_ref::<NuccAnmStrm>()
+                            .map(|anmstrm| (page_index, anmstrm))
+                            .ok_or_else(|| StrmConvError::DowncastFailed {
+                                chunk_type: NuccChunkType::NuccChunkAnmStrm.to_string(),
+                            }),
+                    )
                 } else {
                     None
                 }
             })
         })
-        .next()
-        .unwrap();
+        .find(|anmstrm| {
+            anmstrm.as_ref().map_or(true, |(_, anmstrm)| {
+                anmstrm.struct_info.chunk_name == chunk_name
+            })
+        })
+        .ok_or(StrmConvError::NoAnmStrmChunk)??;
 
     let anmstrmframes = xfbin
         .pages
@@ -49,59 +212,66 @@ pub fn convert_anmstrm(
         .flat_map(|page| {
             page.structs.iter().filter_map(|nucc_struct| {
                 if let NuccChunkType::NuccChunkAnmStrmFrame = nucc_struct.chunk_type() {
-                    Some(nucc_struct.downcast_ref::<NuccAnmStrmFrame>().unwrap())
+                    Some(
+                        nucc_struct
+                            .downcast_ref::<NuccAnmStrmFrame>()
+                            .ok_or_else(|| StrmConvError::DowncastFailed {
+                                chunk_type: NuccChunkType::NuccChunkAnmStrmFrame.to_string(),
+                            }),
+                    )
                 } else {
                     None
                 }
             })
         })
-        .collect::<Vec<_>>();
-
-    let anmstrm_entries = build_anmstrm_entries_map(anmstrmframes)?;
-
-    let anm_entries = convert_entries(anmstrm_entries);
-
-    println!("building anm files...");
-    let mut anm = build_anm(anmstrm, anm_entries, anm_struct_info)?;
-    let dmg_anm = build_dmg_anm(
-        &mut anm,
-        anmstrm,
-        dmg_struct_info,
-        struct_references,
-        struct_infos,
-    ); // Consumes the original anm to create the anm from the dmg clump and mutates the original anm
-
-    let mut anms: Vec<NuccAnm> = Vec::with_capacity(2);
-
-    anms.push(anm);
-    anms.push(dmg_anm);
+        .filter(|anmstrmframe| {
+            anmstrmframe.as_ref().map_or(true, |anmstrmframe| {
+                anmstrmframe.struct_info.chunk_name == anmstrm.struct_info.chunk_name
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
 
-    Ok(anms)
+    Ok((page_index, anmstrm, anmstrmframes))
 }
 
-/// Builds entries from ANMSTRM frames and returns a vector of entries.
-fn build_entries_from_frames(anmstrmframes: Vec<&NuccAnmStrmFrame>) -> Vec<Vec<AnmStrmEntry>> {
-    let entry_count = anmstrmframes
-        .first()
-        .map_or(0, |frame| frame.entries.len() as u32);
-
-    let mut anmstrm_entries: Vec<Vec<AnmStrmEntry>> = vec![Vec::new(); entry_count as usize];
-
-    anmstrm_entries
-        .par_iter_mut()
-        .for_each(|entry| *entry = Vec::new());
-
-    let pb = ProgressBar::new(anmstrmframes.len() as u64);
-    pb.set_style(
-        ProgressStyle::with_template(
-            "gathering frames...   {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}",
-        )
-        .unwrap()
-        .progress_chars("||-"),
+/// Clump index, coord index and entry type that the entries of one ANM entry share.
+type EntryGroupKey = (i16, u16, Discriminant<Entry>);
+
+/// Builds entries from ANMSTRM frames and returns a vector of entries with the tick of their frame.
+///
+/// Frames are ordered by their frame number and a repeated frame number is only used once.
+/// Entries are grouped by their coord and entry type rather than their position in the frame,
+/// so an entry missing from a frame doesn't move the later ones into the wrong group. Groups
+/// are in the order they first appear. Entries xfbin couldn't parse are left out and added to
+/// `skipped_entries`.
+pub(crate) fn build_entries_from_frames(
+    anmstrmframes: Vec<&NuccAnmStrmFrame>,
+    skipped_entries: &mut Vec<SkippedEntry>,
+    quiet: bool,
+) -> Vec<Vec<(i32, AnmStrmEntry)>> {
+    let mut anmstrmframes = anmstrmframes;
+    anmstrmframes.sort_by_key(|anmstrmframe| anmstrmframe.frame);
+    anmstrmframes.dedup_by_key(|anmstrmframe| anmstrmframe.frame);
+
+    let mut anmstrm_entries: Vec<Vec<(i32, AnmStrmEntry)>> = Vec::new();
+    let mut group_indices: HashMap<EntryGroupKey, usize> = HashMap::new();
+
+    let pb = progress_bar(
+        anmstrmframes.len() as u64,
+        "gathering frames...   {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}",
+        quiet,
     );
 
+    let mut skipped: HashMap<EntryGroupKey, SkippedEntry> = HashMap::new();
+
     for (i, anmstrmframe) in anmstrmframes.iter().enumerate() {
-        for (entry_index, entry) in anmstrmframe.entries.iter().enumerate() {
+        for entry in &anmstrmframe.entries {
+            let key = (
+                entry.coord.clump_index,
+                entry.coord.coord_index,
+                mem::discriminant(&entry.entry_data),
+            );
+
             match &entry.entry_data {
                 Entry::Bone(_)
                 | Entry::Material(_)
@@ -110,9 +280,24 @@ fn build_entries_from_frames(anmstrmframes: Vec<&NuccAnmStrmFrame>) -> Vec<Vec<A
                 | Entry::LightPoint(_)
                 | Entry::Ambient(_)
                 | Entry::MorphModel(_) => {
-                    anmstrm_entries[entry_index].push(entry.clone());
+                    let group = *group_indices.entry(key).or_insert_with(|| {
+                        anmstrm_entries.push(Vec::new());
+                        anmstrm_entries.len() - 1
+                    });
+
+                    anmstrm_entries[group].push((anmstrmframe.frame as i32, entry.clone()));
+                }
+                // Entries xfbin couldn't parse have no known layout to build curves from
+                Entry::Unknown(_) => {
+                    skipped
+                        .entry(key)
+                        .or_insert_with(|| SkippedEntry {
+                            kind: entry_kind(entry),
+                            coord: entry.coord.clone(),
+                            frames: 0,
+                        })
+                        .frames += 1;
                 }
-                _ => {}
             }
         }
 
@@ -122,39 +307,144 @@ fn build_entries_from_frames(anmstrmframes: Vec<&NuccAnmStrmFrame>) -> Vec<Vec<A
 
     pb.finish_with_message("done");
 
+    let mut skipped = skipped.into_values().collect::<Vec<_>>();
+    skipped.sort_by(|a, b| {
+        a.coord
+            .clump_index
+            .cmp(&b.coord.clump_index)
+            .then_with(|| a.coord.coord_index.cmp(&b.coord.coord_index))
+            .then_with(|| a.kind.cmp(&b.kind))
+    });
+    skipped_entries.extend(skipped);
+
     anmstrm_entries
 }
 
-/// Builds a map of ANMSTRM entries, where the key is the entry index.
+/// Returns the entry format of an entry type, the same for ANMSTRM and ANM entries.
+///
+/// Entries xfbin couldn't parse have no known format.
+pub(crate) fn entry_format(entry_data: &Entry) -> Option<u16> {
+    let entry_format = match entry_data {
+        Entry::Bone(_) => AnmEntryFormat::BONE,
+        Entry::Material(_) => AnmEntryFormat::MATERIAL,
+        Entry::Camera(_) => AnmEntryFormat::CAMERA,
+        Entry::LightDirc(_) => AnmEntryFormat::LIGHTDIRC,
+        Entry::LightPoint(_) => AnmEntryFormat::LIGHTPOINT,
+        Entry::Ambient(_) => AnmEntryFormat::AMBIENT,
+        Entry::MorphModel(_) => AnmEntryFormat::MORPHMODEL,
+        Entry::Unknown(_) => return None,
+    };
+
+    Some(entry_format as u16)
+}
+
+/// Returns the name of an entry's type, with its entry format when xfbin couldn't parse it.
+fn entry_kind(entry: &AnmStrmEntry) -> String {
+    match &entry.entry_data {
+        Entry::Bone(_) => "Bone".to_string(),
+        Entry::Material(_) => "Material".to_string(),
+        Entry::Camera(_) => "Camera".to_string(),
+        Entry::LightDirc(_) => "LightDirc".to_string(),
+        Entry::LightPoint(_) => "LightPoint".to_string(),
+        Entry::Ambient(_) => "Ambient".to_string(),
+        Entry::MorphModel(_) => "MorphModel".to_string(),
+        Entry::Unknown(_) => format!("Unknown (entry format {})", entry.entry_format),
+    }
+}
+
+/// Builds a map of ANMSTRM entries, where the key is the index of the entry's group.
 fn build_anmstrm_entries_map(
     anmstrmframes: Vec<&NuccAnmStrmFrame>,
-) -> Result<HashMap<u16, Vec<AnmStrmEntry>>, Box<dyn Error>> {
-    let anmstrm_entries = build_entries_from_frames(anmstrmframes);
+    skipped_entries: &mut Vec<SkippedEntry>,
+    quiet: bool,
+) -> Result<HashMap<u16, Vec<(i32, AnmStrmEntry)>>, StrmConvError> {
+    let anmstrm_entries = build_entries_from_frames(anmstrmframes, skipped_entries, quiet);
 
-    Ok(anmstrm_entries
+    anmstrm_entries
         .into_iter()
         .enumerate()
         .filter(|(_, entries)| !entries.is_empty())
-        .map(|(entry_index, entries)| (entry_index.try_into().unwrap(), entries))
-        .collect())
+        .map(|(entry_index, entries)| {
+            let index = u16::try_from(entry_index)
+                .map_err(|_| StrmConvError::EntryIndexOverflow { index: entry_index })?;
+
+            Ok((index, entries))
+        })
+        .collect()
+}
+
+/// Compresses a rotation into the short quaternion format.
+///
+/// The rotation is normalised, flipped to the hemisphere of the previous rotation so the
+/// game never interpolates the long way round, then rounded and clamped to the short range.
+pub fn compress_quaternion(rotation: &Vector4, previous: &mut Option<Vector4>) -> QuaternionShort {
+    let mut rotation = normalize_quaternion(rotation);
+
+    if let Some(previous) = previous {
+        if quaternion_dot(previous, &rotation) < 0.0 {
+            rotation = Vector4 {
+                x: -rotation.x,
+                y: -rotation.y,
+                z: -rotation.z,
+                w: -rotation.w,
+            };
+        }
+    }
+
+    let compress = |value: f32| {
+        (value * QUAT_COMPRESS)
+            .round()
+            .clamp(-QUAT_COMPRESS, QUAT_COMPRESS) as i16
+    };
+
+    let compressed = QuaternionShort {
+        x: compress(rotation.x),
+        y: compress(rotation.y),
+        z: compress(rotation.z),
+        w: compress(rotation.w),
+    };
+
+    *previous = Some(rotation);
+
+    compressed
+}
+
+/// Pushes the value of a curve that stores one value per frame at the given frame.
+///
+/// Frames an entry is missing from hold the previous value, or the first value when the
+/// entry starts after the first frame.
+fn push_frame_value<T: Clone>(values: &mut Vec<T>, frame: usize, value: T) {
+    if values.len() < frame {
+        let hold = values.last().cloned().unwrap_or_else(|| value.clone());
+        values.resize(frame, hold);
+    }
+
+    values.push(value);
 }
 
 /// Converts ANMSTRM entries map into a vector of ANM entries.
-fn convert_entries(anmstrm_entries: HashMap<u16, Vec<AnmStrmEntry>>) -> Vec<AnmEntry> {
+///
+/// Keys are placed at the tick of the frame they came from. Curves that store one value per
+/// frame hold the previous value over frames an entry is missing from, so later frames don't shift.
+fn convert_entries(
+    anmstrm_entries: HashMap<u16, Vec<(i32, AnmStrmEntry)>>,
+    settings: &CurveSettings,
+    report: &mut ConversionReport,
+    quiet: bool,
+) -> Vec<AnmEntry> {
+    let frame_ticks = settings.frame_ticks;
+
     let mut anm_entries: Vec<AnmEntry> = Vec::with_capacity(anmstrm_entries.len());
 
-    let pb = ProgressBar::new(anmstrm_entries.len() as u64);
-    pb.set_style(
-        ProgressStyle::with_template(
-            "converting entries... {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}",
-        )
-        .unwrap()
-        .progress_chars("||-"),
+    let pb = progress_bar(
+        anmstrm_entries.len() as u64,
+        "converting entries... {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}",
+        quiet,
     );
 
     for (i, entry) in &anmstrm_entries {
         let mut anm_entry = AnmEntry {
-            coord: entry[0].coord.clone(),
+            coord: entry[0].1.coord.clone(),
             entry_format: 0,
             curve_count: 0,
             curve_headers: Vec::new(),
@@ -162,18 +452,21 @@ fn convert_entries(anmstrm_entries: HashMap<u16, Vec<AnmStrmEntry>>) -> Vec<AnmE
         };
 
         let mut curve_index = 0; // Index for the curve headers
+        let mut previous_rotation: Option<Vector4> = None; // Last rotation, for sign continuity
+
+        // Material colors get a second key half a frame later
+        let half_frame = frame_ticks / 2;
+
+        for (key_index, (tick, anmstrm_entry)) in entry.iter().enumerate() {
+            let (tick, first_key) = (*tick, key_index == 0);
+            let frame = (tick / frame_ticks) as usize;
 
-        for (frame, anmstrm_entry) in entry
-            .iter()
-            .enumerate()
-            .map(|(frame, entry)| (frame * 100, entry))
-        {
             match &anmstrm_entry.entry_data {
                 // ----------------- BONE -----------------
                 Entry::Bone(anmstrm_entry_bone) => {
                     anm_entry.entry_format = AnmEntryFormat::BONE as u16;
 
-                    if frame == 0 {
+                    if first_key {
                         // Create curves and curve headers for location, rotation, scale, and toggled
                         anm_entry.curves.push(Curve::KeyframeVector3(Vec::new()));
                         anm_entry.curves.push(Curve::QuaternionShort(Vec::new()));
@@ -181,7 +474,7 @@ fn convert_entries(anmstrm_entries: HashMap<u16, Vec<AnmStrmEntry>>) -> Vec<AnmE
                         anm_entry.curves.push(Curve::Float(Vec::new()));
 
                         anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index,
+                            curve_index,
                             curve_format: AnmCurveFormat::INT1_FLOAT3 as u16, // Curve format for keyframe location
                             frame_count: 0,
                             curve_size: 0,
@@ -210,28 +503,30 @@ fn convert_entries(anmstrm_entries: HashMap<u16, Vec<AnmStrmEntry>>) -> Vec<AnmE
                     // Push keyframes for location, rotation, scale, and toggled
                     if let Curve::KeyframeVector3(location_keyframes) = &mut anm_entry.curves[0] {
                         location_keyframes.push(KeyframeVector3 {
-                            frame: frame as i32,
+                            frame: tick,
                             value: anmstrm_entry_bone.location.clone(),
                         });
                     }
                     if let Curve::QuaternionShort(rotation_keyframes) = &mut anm_entry.curves[1] {
-                        rotation_keyframes.push(QuaternionShort {
-                            x: (anmstrm_entry_bone.rotation.x * QUAT_COMPRESS) as i16,
-                            y: (anmstrm_entry_bone.rotation.y * QUAT_COMPRESS) as i16,
-                            z: (anmstrm_entry_bone.rotation.z * QUAT_COMPRESS) as i16,
-                            w: (anmstrm_entry_bone.rotation.w * QUAT_COMPRESS) as i16,
-                        });
+                        push_frame_value(
+                            rotation_keyframes,
+                            frame,
+                            compress_quaternion(
+                                &anmstrm_entry_bone.rotation,
+                                &mut previous_rotation,
+                            ),
+                        );
                     }
 
                     if let Curve::KeyframeVector3(scale_keyframes) = &mut anm_entry.curves[2] {
                         scale_keyframes.push(KeyframeVector3 {
-                            frame: frame as i32,
+                            frame: tick,
                             value: anmstrm_entry_bone.scale.clone(),
                         });
                     }
 
                     if let Curve::Float(toggled_value) = &mut anm_entry.curves[3] {
-                        toggled_value.push(anmstrm_entry_bone.toggled);
+                        push_frame_value(toggled_value, frame, anmstrm_entry_bone.toggled);
                     }
                 }
 
@@ -239,7 +534,7 @@ fn convert_entries(anmstrm_entries: HashMap<u16, Vec<AnmStrmEntry>>) -> Vec<AnmE
                 Entry::Material(anmstrm_entry_material) => {
                     anm_entry.entry_format = AnmEntryFormat::MATERIAL as u16;
 
-                    if frame == 0 {
+                    if first_key {
                         anm_entry.curves.push(Curve::KeyframeFloat(Vec::new()));
                         anm_entry.curves.push(Curve::KeyframeFloat(Vec::new()));
 
@@ -263,7 +558,7 @@ fn convert_entries(anmstrm_entries: HashMap<u16, Vec<AnmStrmEntry>>) -> Vec<AnmE
                         anm_entry.curves.push(Curve::Float(Vec::new()));
 
                         anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index,
+                            curve_index,
                             curve_format: AnmCurveFormat::INT1_FLOAT1 as u16, // Curve format for KeyframeFloat
                             frame_count: 0,
                             curve_size: 0,
@@ -394,101 +689,149 @@ fn convert_entries(anmstrm_entries: HashMap<u16, Vec<AnmStrmEntry>>) -> Vec<AnmE
                     // Push keyframes for material color and toggled
                     if let Curve::KeyframeFloat(color_values) = &mut anm_entry.curves[0] {
                         color_values.push(KeyframeFloat {
-                            frame: frame as i32,
+                            frame: tick,
                             value: anmstrm_entry_material.ambient_color[0],
                         });
 
                         color_values.push(KeyframeFloat {
-                            frame: frame as i32 + 50,
+                            frame: tick + half_frame,
                             value: anmstrm_entry_material.ambient_color[0],
                         });
                     }
 
                     if let Curve::KeyframeFloat(color_values) = &mut anm_entry.curves[1] {
                         color_values.push(KeyframeFloat {
-                            frame: frame as i32,
+                            frame: tick,
                             value: anmstrm_entry_material.ambient_color[1],
                         });
 
                         color_values.push(KeyframeFloat {
-                            frame: frame as i32 + 50,
+                            frame: tick + half_frame,
                             value: anmstrm_entry_material.ambient_color[1],
                         });
                     }
 
                     if let Curve::Float(color_values) = &mut anm_entry.curves[2] {
-                        color_values.push(anmstrm_entry_material.ambient_color[2]);
+                        push_frame_value(
+                            color_values,
+                            frame,
+                            anmstrm_entry_material.ambient_color[2],
+                        );
                     }
 
                     if let Curve::Float(color_values) = &mut anm_entry.curves[3] {
-                        color_values.push(anmstrm_entry_material.ambient_color[3]);
+                        push_frame_value(
+                            color_values,
+                            frame,
+                            anmstrm_entry_material.ambient_color[3],
+                        );
                     }
 
                     if let Curve::Float(color_values) = &mut anm_entry.curves[4] {
-                        color_values.push(anmstrm_entry_material.ambient_color[4]);
+                        push_frame_value(
+                            color_values,
+                            frame,
+                            anmstrm_entry_material.ambient_color[4],
+                        );
                     }
 
                     if let Curve::Float(color_values) = &mut anm_entry.curves[5] {
-                        color_values.push(anmstrm_entry_material.ambient_color[5]);
+                        push_frame_value(
+                            color_values,
+                            frame,
+                            anmstrm_entry_material.ambient_color[5],
+                        );
                     }
 
                     if let Curve::Float(color_values) = &mut anm_entry.curves[6] {
-                        color_values.push(anmstrm_entry_material.ambient_color[6]);
+                        push_frame_value(
+                            color_values,
+                            frame,
+                            anmstrm_entry_material.ambient_color[6],
+                        );
                     }
 
                     if let Curve::Float(color_values) = &mut anm_entry.curves[7] {
-                        color_values.push(anmstrm_entry_material.ambient_color[7]);
+                        push_frame_value(
+                            color_values,
+                            frame,
+                            anmstrm_entry_material.ambient_color[7],
+                        );
                     }
 
                     if let Curve::KeyframeFloat(color_values) = &mut anm_entry.curves[8] {
                         color_values.push(KeyframeFloat {
-                            frame: frame as i32,
+                            frame: tick,
                             value: anmstrm_entry_material.ambient_color[8],
                         });
 
                         color_values.push(KeyframeFloat {
-                            frame: frame as i32 + 50,
+                            frame: tick + half_frame,
                             value: anmstrm_entry_material.ambient_color[8],
                         });
                     }
 
                     if let Curve::KeyframeFloat(color_values) = &mut anm_entry.curves[9] {
                         color_values.push(KeyframeFloat {
-                            frame: frame as i32,
+                            frame: tick,
                             value: anmstrm_entry_material.ambient_color[9],
                         });
 
                         color_values.push(KeyframeFloat {
-                            frame: frame as i32 + 50,
+                            frame: tick + half_frame,
                             value: anmstrm_entry_material.ambient_color[9],
                         });
                     }
 
                     if let Curve::Float(color_values) = &mut anm_entry.curves[10] {
-                        color_values.push(anmstrm_entry_material.ambient_color[10]);
+                        push_frame_value(
+                            color_values,
+                            frame,
+                            anmstrm_entry_material.ambient_color[10],
+                        );
                     }
 
                     if let Curve::Float(color_values) = &mut anm_entry.curves[11] {
-                        color_values.push(anmstrm_entry_material.ambient_color[11]);
+                        push_frame_value(
+                            color_values,
+                            frame,
+                            anmstrm_entry_material.ambient_color[11],
+                        );
                     }
 
                     if let Curve::Float(color_values) = &mut anm_entry.curves[12] {
-                        color_values.push(anmstrm_entry_material.ambient_color[12]);
+                        push_frame_value(
+                            color_values,
+                            frame,
+                            anmstrm_entry_material.ambient_color[12],
+                        );
                     }
 
                     if let Curve::Float(color_values) = &mut anm_entry.curves[13] {
-                        color_values.push(anmstrm_entry_material.ambient_color[13]);
+                        push_frame_value(
+                            color_values,
+                            frame,
+                            anmstrm_entry_material.ambient_color[13],
+                        );
                     }
 
                     if let Curve::Float(color_values) = &mut anm_entry.curves[14] {
-                        color_values.push(anmstrm_entry_material.ambient_color[14]);
+                        push_frame_value(
+                            color_values,
+                            frame,
+                            anmstrm_entry_material.ambient_color[14],
+                        );
                     }
 
                     if let Curve::Float(color_values) = &mut anm_entry.curves[15] {
-                        color_values.push(anmstrm_entry_material.ambient_color[15]);
+                        push_frame_value(
+                            color_values,
+                            frame,
+                            anmstrm_entry_material.ambient_color[15],
+                        );
                     }
 
-                    if frame == 0 {
+                    if first_key {
                         if let Curve::Float(color_values) = &mut anm_entry.curves[16] {
                             color_values.push(0.0);
                         }
@@ -502,14 +845,14 @@ fn convert_entries(anmstrm_entries: HashMap<u16, Vec<AnmStrmEntry>>) -> Vec<AnmE
                 Entry::Camera(anmstrm_entry_camera) => {
                     anm_entry.entry_format = AnmEntryFormat::CAMERA as u16;
 
-                    if frame == 0 {
+                    if first_key {
                         // Create curves and curve headers for location, rotation, fov
                         anm_entry.curves.push(Curve::KeyframeVector3(Vec::new()));
                         anm_entry.curves.push(Curve::QuaternionShort(Vec::new()));
                         anm_entry.curves.push(Curve::KeyframeFloat(Vec::new()));
 
                         anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index,
+                            curve_index,
                             curve_format: AnmCurveFormat::INT1_FLOAT3 as u16, // Curve format for KeyframeVector3
                             frame_count: 0,
                             curve_size: 0,
@@ -535,21 +878,23 @@ fn convert_entries(anmstrm_entries: HashMap<u16, Vec<AnmStrmEntry>>) -> Vec<AnmE
                     // Push keyframes for location, rotation, fov
                     if let Curve::KeyframeVector3(location_keyframes) = &mut anm_entry.curves[0] {
                         location_keyframes.push(KeyframeVector3 {
-                            frame: frame as i32,
+                            frame: tick,
                             value: anmstrm_entry_camera.location.clone(),
                         });
                     }
                     if let Curve::QuaternionShort(rotation_keyframes) = &mut anm_entry.curves[1] {
-                        rotation_keyframes.push(QuaternionShort {
-                            x: (anmstrm_entry_camera.rotation.x * QUAT_COMPRESS) as i16,
-                            y: (anmstrm_entry_camera.rotation.y * QUAT_COMPRESS) as i16,
-                            z: (anmstrm_entry_camera.rotation.z * QUAT_COMPRESS) as i16,
-                            w: (anmstrm_entry_camera.rotation.w * QUAT_COMPRESS) as i16,
-                        });
+                        push_frame_value(
+                            rotation_keyframes,
+                            frame,
+                            compress_quaternion(
+                                &anmstrm_entry_camera.rotation,
+                                &mut previous_rotation,
+                            ),
+                        );
                     }
                     if let Curve::KeyframeFloat(fov_keyframes) = &mut anm_entry.curves[2] {
                         fov_keyframes.push(KeyframeFloat {
-                            frame: frame as i32,
+                            frame: tick,
                             value: anmstrm_entry_camera.fov,
                         });
                     }
@@ -559,13 +904,13 @@ fn convert_entries(anmstrm_entries: HashMap<u16, Vec<AnmStrmEntry>>) -> Vec<AnmE
                 Entry::LightDirc(anmstrm_entry_lightdir) => {
                     anm_entry.entry_format = AnmEntryFormat::LIGHTDIRC as u16;
 
-                    if frame == 0 {
+                    if first_key {
                         anm_entry.curves.push(Curve::RGB(Vec::new()));
                         anm_entry.curves.push(Curve::Float(Vec::new()));
                         anm_entry.curves.push(Curve::QuaternionShort(Vec::new()));
 
                         anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index,
+                            curve_index,
                             curve_format: AnmCurveFormat::BYTE3 as u16, // Curve format for RGB
                             frame_count: 0,
                             curve_size: 0,
@@ -589,24 +934,30 @@ fn convert_entries(anmstrm_entries: HashMap<u16, Vec<AnmStrmEntry>>) -> Vec<AnmE
                     }
                     // Push keyframes for color, light strength, rotations
                     if let Curve::RGB(color_values) = &mut anm_entry.curves[0] {
-                        color_values.push(RGB {
-                            r: (anmstrm_entry_lightdir.color.x * RGB_CONVERT) as u8,
-                            g: (anmstrm_entry_lightdir.color.y * RGB_CONVERT) as u8,
-                            b: (anmstrm_entry_lightdir.color.z * RGB_CONVERT) as u8,
-                        });
+                        push_frame_value(
+                            color_values,
+                            frame,
+                            RGB {
+                                r: (anmstrm_entry_lightdir.color.x * RGB_CONVERT) as u8,
+                                g: (anmstrm_entry_lightdir.color.y * RGB_CONVERT) as u8,
+                                b: (anmstrm_entry_lightdir.color.z * RGB_CONVERT) as u8,
+                            },
+                        );
                     }
 
                     if let Curve::Float(strength_values) = &mut anm_entry.curves[1] {
-                        strength_values.push(anmstrm_entry_lightdir.intensity);
+                        push_frame_value(strength_values, frame, anmstrm_entry_lightdir.intensity);
                     }
 
                     if let Curve::QuaternionShort(rotation_keyframes) = &mut anm_entry.curves[2] {
-                        rotation_keyframes.push(QuaternionShort {
-                            x: (anmstrm_entry_lightdir.direction.x * QUAT_COMPRESS) as i16,
-                            y: (anmstrm_entry_lightdir.direction.y * QUAT_COMPRESS) as i16,
-                            z: (anmstrm_entry_lightdir.direction.z * QUAT_COMPRESS) as i16,
-                            w: (anmstrm_entry_lightdir.direction.w * QUAT_COMPRESS) as i16,
-                        });
+                        push_frame_value(
+                            rotation_keyframes,
+                            frame,
+                            compress_quaternion(
+                                &anmstrm_entry_lightdir.direction,
+                                &mut previous_rotation,
+                            ),
+                        );
                     }
                 }
 
@@ -614,7 +965,7 @@ fn convert_entries(anmstrm_entries: HashMap<u16, Vec<AnmStrmEntry>>) -> Vec<AnmE
                 Entry::LightPoint(anm_entry_lightpoint) => {
                     anm_entry.entry_format = AnmEntryFormat::LIGHTPOINT as u16;
 
-                    if frame == 0 {
+                    if first_key {
                         // Create curves and curve headers for color, light strength, location
                         anm_entry.curves.push(Curve::RGB(Vec::new()));
                         anm_entry.curves.push(Curve::Float(Vec::new()));
@@ -623,7 +974,7 @@ fn convert_entries(anmstrm_entries: HashMap<u16, Vec<AnmStrmEntry>>) -> Vec<AnmE
                         anm_entry.curves.push(Curve::Float(Vec::new()));
 
                         anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index,
+                            curve_index,
                             curve_format: AnmCurveFormat::BYTE3 as u16, // Curve format for RGB
                             frame_count: 0,
                             curve_size: 0,
@@ -662,43 +1013,47 @@ fn convert_entries(anmstrm_entries: HashMap<u16, Vec<AnmStrmEntry>>) -> Vec<AnmE
 
                     // Push keyframes for color, light strength, location
                     if let Curve::RGB(color_values) = &mut anm_entry.curves[0] {
-                        color_values.push(RGB {
-                            r: (anm_entry_lightpoint.color.x * RGB_CONVERT) as u8,
-                            g: (anm_entry_lightpoint.color.y * RGB_CONVERT) as u8,
-                            b: (anm_entry_lightpoint.color.z * RGB_CONVERT) as u8,
-                        });
+                        push_frame_value(
+                            color_values,
+                            frame,
+                            RGB {
+                                r: (anm_entry_lightpoint.color.x * RGB_CONVERT) as u8,
+                                g: (anm_entry_lightpoint.color.y * RGB_CONVERT) as u8,
+                                b: (anm_entry_lightpoint.color.z * RGB_CONVERT) as u8,
+                            },
+                        );
                     }
 
                     if let Curve::Float(intensity_values) = &mut anm_entry.curves[1] {
-                        intensity_values.push(anm_entry_lightpoint.intensity);
+                        push_frame_value(intensity_values, frame, anm_entry_lightpoint.intensity);
                     }
 
                     if let Curve::KeyframeVector3(location_keyframes) = &mut anm_entry.curves[2] {
                         location_keyframes.push(KeyframeVector3 {
-                            frame: frame as i32,
+                            frame: tick,
                             value: anm_entry_lightpoint.position.clone(),
                         });
                     }
 
                     if let Curve::Float(radius_values) = &mut anm_entry.curves[3] {
-                        radius_values.push(anm_entry_lightpoint.radius);
+                        push_frame_value(radius_values, frame, anm_entry_lightpoint.radius);
                     }
 
                     if let Curve::Float(falloff_values) = &mut anm_entry.curves[4] {
-                        falloff_values.push(anm_entry_lightpoint.falloff);
+                        push_frame_value(falloff_values, frame, anm_entry_lightpoint.falloff);
                     }
                 }
 
                 Entry::Ambient(anm_entry_ambient) => {
                     anm_entry.entry_format = AnmEntryFormat::AMBIENT as u16;
 
-                    if frame == 0 {
+                    if first_key {
                         // Create curves and curve headers for color, light strength
                         anm_entry.curves.push(Curve::RGB(Vec::new()));
                         anm_entry.curves.push(Curve::Float(Vec::new()));
 
                         anm_entry.curve_headers.push(CurveHeader {
-                            curve_index: curve_index,
+                            curve_index,
                             curve_format: AnmCurveFormat::BYTE3 as u16, // Curve format for RGB
                             frame_count: 0,
                             curve_size: 0,
@@ -716,22 +1071,26 @@ fn convert_entries(anmstrm_entries: HashMap<u16, Vec<AnmStrmEntry>>) -> Vec<AnmE
 
                     // Push keyframes for color, light strength
                     if let Curve::RGB(color_values) = &mut anm_entry.curves[0] {
-                        color_values.push(RGB {
-                            r: (anm_entry_ambient.color.x * RGB_CONVERT) as u8,
-                            g: (anm_entry_ambient.color.y * RGB_CONVERT) as u8,
-                            b: (anm_entry_ambient.color.z * RGB_CONVERT) as u8,
-                        });
+                        push_frame_value(
+                            color_values,
+                            frame,
+                            RGB {
+                                r: (anm_entry_ambient.color.x * RGB_CONVERT) as u8,
+                                g: (anm_entry_ambient.color.y * RGB_CONVERT) as u8,
+                                b: (anm_entry_ambient.color.z * RGB_CONVERT) as u8,
+                            },
+                        );
                     }
 
                     if let Curve::Float(strength_values) = &mut anm_entry.curves[1] {
-                        strength_values.push(anm_entry_ambient.intensity);
+                        push_frame_value(strength_values, frame, anm_entry_ambient.intensity);
                     }
                 }
 
                 Entry::MorphModel(anm_entry_morphmodel) => {
                     anm_entry.entry_format = AnmEntryFormat::MORPHMODEL as u16;
 
-                    if frame == 0 {
+                    if first_key {
                         anm_entry.curves.push(Curve::Float(Vec::new()));
                         anm_entry.curves.push(Curve::Float(Vec::new()));
 
@@ -755,48 +1114,57 @@ fn convert_entries(anmstrm_entries: HashMap<u16, Vec<AnmStrmEntry>>) -> Vec<AnmE
                     // Push keyframes for morph model
                     if anm_entry_morphmodel.frame_count == 1 {
                         if let Curve::Float(morph_value) = &mut anm_entry.curves[0] {
-                            morph_value.push(anm_entry_morphmodel.morph_weight[0]);
+                            push_frame_value(
+                                morph_value,
+                                frame,
+                                anm_entry_morphmodel.morph_weight[0],
+                            );
                         }
                     } else if anm_entry_morphmodel.frame_count == 2 {
                         if let Curve::Float(morph_value) = &mut anm_entry.curves[0] {
-                            morph_value.push(anm_entry_morphmodel.morph_weight[0]);
+                            push_frame_value(
+                                morph_value,
+                                frame,
+                                anm_entry_morphmodel.morph_weight[0],
+                            );
                         }
 
                         if let Curve::Float(morph_value) = &mut anm_entry.curves[1] {
-                            morph_value.push(anm_entry_morphmodel.morph_weight[1]);
+                            push_frame_value(
+                                morph_value,
+                                frame,
+                                anm_entry_morphmodel.morph_weight[1],
+                            );
                         }
                     }
                 }
-                _ => {
-                    // Handle other entry types if necessary
-                    // ...
-                }
+                // Left out by build_entries_from_frames
+                Entry::Unknown(_) => {}
             }
         }
-        // Update curves and headers for necessary changes
-        for (curve, curve_header) in anm_entry
-            .curves
-            .iter_mut()
-            .zip(&mut anm_entry.curve_headers)
-        {
-            if curve.has_keyframes() {
-                curve.append_null_keyframe();
-                curve_header.frame_count += 1;
-            }
+        if let Some(time_scale) = settings.time_scale {
+            resample_entry(&mut anm_entry, time_scale, frame_ticks);
+        }
 
-            //If curve is RBG we need to pad the color values to be a multiple of 4
-            if curve.get_curve_format() == AnmCurveFormat::BYTE3 as u16
-                || curve.get_curve_format() == AnmCurveFormat::SHORT3 as u16
-            {
-                curve.pad_values();
-            }
-            // Make sure we update the frame count and curve size for the curves
-            curve_header.curve_size += 0xC;
-            //(size_of_val(&curve) * curve.get_frame_count() as usize) as u16;
-            curve_header.frame_count = curve.get_frame_count() as u16;
+        if settings.close_loop {
+            close_loop(&mut anm_entry, frame_ticks, &mut report.loop_pops);
+        }
+
+        // Remove redundant bone keys before the curve headers are sized
+        if let Some(keyframe_reduction) = settings.keyframe_reduction {
+            reduce_bone_curves(
+                &mut anm_entry,
+                keyframe_reduction,
+                frame_ticks,
+                &mut report.reduction,
+            );
+        }
+
+        if settings.collapse_constants {
+            collapse_constant_curves(&mut anm_entry, &mut report.reduction);
         }
 
-        anm_entry.curve_count = anm_entry.curves.len() as u16;
+        finish_curves(&mut anm_entry);
         anm_entries.push(anm_entry);
         pb.set_message(format!("entry #{}", i + 1));
         pb.inc(1);
@@ -807,12 +1175,42 @@ fn convert_entries(anmstrm_entries: HashMap<u16, Vec<AnmStrmEntry>>) -> Vec<AnmE
     anm_entries
 }
 
+/// Appends the null keyframes, pads the colors and sizes the curve headers of a finished entry.
+///
+/// Every other change to the curves has to be done before this.
+pub(crate) fn finish_curves(anm_entry: &mut AnmEntry) {
+    for (curve, curve_header) in anm_entry
+        .curves
+        .iter_mut()
+        .zip(&mut anm_entry.curve_headers)
+    {
+        if curve.has_keyframes() {
+            curve.append_null_keyframe();
+            curve_header.frame_count += 1;
+        }
+
+        //If curve is RBG we need to pad the color values to be a multiple of 4
+        if curve.get_curve_format() == AnmCurveFormat::BYTE3 as u16
+            || curve.get_curve_format() == AnmCurveFormat::SHORT3 as u16
+        {
+            curve.pad_values();
+        }
+        // Make sure we update the frame count and curve size for the curves
+        curve_header.curve_size += 0xC;
+        //(size_of_val(&curve) * curve.get_frame_count() as usize) as u16;
+        curve_header.frame_count = curve.get_frame_count() as u16;
+    }
+
+    anm_entry.curve_count = anm_entry.curves.len() as u16;
+}
+
 /// Builds an ANM object from ANMSTRM and converted ANM entries.
 pub fn build_anm(
     anmstrm: &NuccAnmStrm,
     anm_entries: Vec<AnmEntry>,
     struct_info: &NuccStructInfo,
-) -> Result<NuccAnm, Box<dyn Error>> {
+    frame_ticks: i32,
+) -> Result<NuccAnm, StrmConvError> {
     let mut anm_entries = anm_entries;
 
     anm_entries.sort_by(|a, b| {
@@ -838,7 +1236,7 @@ pub fn build_anm(
     let anm = NuccAnm {
         struct_info: struct_info.clone(),
         version: 121,
-        frame_count: anmstrm.frame_count - 100,
+        frame_count: anmstrm.frame_count.saturating_sub(frame_ticks as u32),
         is_looped: anmstrm.is_looped,
         clumps: anm_clumps,
         other_entries_indices: anmstrm.other_entry_indices.clone(),
@@ -850,107 +1248,111 @@ pub fn build_anm(
     Ok(anm)
 }
 
-/// Builds a DMG ANM object from the ANM and ANMSTRM.
-fn build_dmg_anm(
-    anm: &mut NuccAnm,
-    anmstrm: &NuccAnmStrm,
-    struct_info: &NuccStructInfo,
-    struct_references: &mut Vec<NuccStructReference>,
-    struct_infos: &mut Vec<NuccStructInfo>,
-) -> NuccAnm {
-    // ----------------- Clumps -----------------
-    let mut dmg_clumps = anm.clumps.clone();
-
-    let dmg_clump_index = dmg_clumps
-        .par_iter()
-        .position_any(|clump| clump.bone_material_indices.len() == 97)
-        .unwrap_or(0);
+/// The positions of the DMG clumps and of the clumps left out of the DMG ANM.
+type DmgClumpSelection = (Vec<usize>, Vec<usize>);
+
+/// Picks the clumps whose entries move to the DMG ANM and the clumps left out of the DMG ANM.
+///
+/// Returns `None` when the DMG ANM is disabled, or when the heuristic finds no clump with 97
+/// bones.
+fn select_dmg_clumps(
+    anm: &NuccAnm,
+    struct_references: &[NuccStructReference],
+    dmg_split: &DmgSplit,
+) -> Result<Option<DmgClumpSelection>, StrmConvError> {
+    let clump_info = |clump: &AnmClump| {
+        struct_references
+            .get(clump.clump_index as usize)
+            .map(|reference| &reference.struct_info)
+    };
 
-    
-    let mut clumps_map: HashMap<usize, AnmClump> = HashMap::new();
+    let selected: Vec<usize> = match dmg_split {
+        DmgSplit::Disabled => return Ok(None),
+        DmgSplit::Heuristic => {
+            // The DMG clump is the one with 97 bones, the clumps with more are left out
+            let dmg_clump_index = match anm
+                .clumps
+                .iter()
+                .position(|clump| clump.bone_material_indices.len() == 97)
+            {
+                Some(dmg_clump_index) => dmg_clump_index,
+                None => return Ok(None),
+            };
+
+            let removed_clump_indices = anm
+                .clumps
+                .iter()
+                .enumerate()
+                .filter(|(_, clump)| clump.bone_material_indices.len() > 97)
+                .map(|(i, _)| i)
+                .collect();
+
+            return Ok(Some((vec![dmg_clump_index], removed_clump_indices)));
+        }
+        DmgSplit::ChunkNames(chunk_names) => (0..anm.clumps.len())
+            .filter(|&i| {
+                clump_info(&anm.clumps[i])
+                    .is_some_and(|info| chunk_names.contains(&info.chunk_name))
+            })
+            .collect(),
+        DmgSplit::FilepathPattern(pattern) => (0..anm.clumps.len())
+            .filter(|&i| {
+                clump_info(&anm.clumps[i]).is_some_and(|info| info.filepath.contains(pattern))
+            })
+            .collect(),
+        DmgSplit::Indices(indices) => {
+            if let Some(&index) = indices.iter().find(|&&index| index >= anm.clumps.len()) {
+                return Err(StrmConvError::DmgClumpIndexOutOfRange {
+                    index,
+                    clump_count: anm.clumps.len(),
+                });
+            }
 
-    // Make a hashmap for clumps that have more than 97 bone material indices, with the key being the index of the clump from the clumps vector
-    for (i, clump) in dmg_clumps.iter().enumerate() {
-        if clump.bone_material_indices.len() > 97 {
-            clumps_map.insert(i, clump.clone());
+            (0..anm.clumps.len())
+                .filter(|i| indices.contains(i))
+                .collect()
         }
-    }
+    };
 
-    // Retain clumps not in the clumps map
-    dmg_clumps.retain(|clump| !clumps_map.values().any(|value| value == clump));
+    if selected.is_empty() {
+        return Err(StrmConvError::NoDmgClumps);
+    }
 
-    // Iterate over the clumps and update the clump indices using the clumps map
-    for clump in &mut dmg_clumps {
-        let clump_index = anm.clumps.iter().position(|r| r == clump).unwrap() as u32;
+    let removed = (0..anm.clumps.len())
+        .filter(|i| !selected.contains(i))
+        .collect();
 
-        for (key, value) in clumps_map.iter() {
-            let amount_to_subtract =
-                1 + (value.bone_material_indices.len() + value.model_indices.len()) as u32;
+    Ok(Some((selected, removed)))
+}
 
-            if clump_index > *key as u32 {
-                clump.clump_index -= amount_to_subtract;
+/// Builds a DMG ANM object from the ANM and ANMSTRM.
+///
+/// The entries of the DMG clumps are moved out of the ANM, and the removed clumps are left out
+/// of the DMG ANM.
+fn build_dmg_anm(
+    anm: &mut NuccAnm,
+    struct_info: &NuccStructInfo,
+    struct_references: &mut Vec<NuccStructReference>,
+    struct_infos: &mut Vec<NuccStructInfo>,
+    dmg_clump_indices: &[usize],
+    removed_clump_indices: &[usize],
+) -> NuccAnm {
+    let remap = ClumpRemap::new(&anm.clumps, struct_references.len(), removed_clump_indices);
 
-                for index in &mut clump.bone_material_indices {
-                    *index -= amount_to_subtract;
-                }
-
-                for index in &mut clump.model_indices {
-                    *index -= amount_to_subtract;
-                }
-            }
-        }
-    }
+    // ----------------- Clumps ----------------- //
+    let dmg_clumps = remap.remap_clumps(&anm.clumps);
 
     // ----------------- References & Maps ----------------- //
-    let chunks_to_remove: Vec<String> = clumps_map
-        .iter()
-        .map(|(_, value)| {
-            struct_references[value.clump_index as usize]
-                .struct_info
-                .chunk_name
-                .clone()
-        })
-        .collect();
+    let kept_references = remap.remap_references(struct_references);
 
-    for chunk in &chunks_to_remove {
-        struct_infos.retain(|info| !info.filepath.contains(chunk));
-        struct_references.retain(|reference| !reference.struct_info.filepath.contains(chunk));
-    }
+    retain_referenced_struct_infos(struct_infos, struct_references, &kept_references);
+    *struct_references = kept_references;
 
     // ----------------- Coords ----------------- //
-    let mut dmg_coord_parents = anm.coord_parents.clone();
-
-    for (key, _) in clumps_map.iter() {
-        dmg_coord_parents.retain(|coord_parent| coord_parent.parent.clump_index != *key as i16);
-    }
-
-    for coord in &mut dmg_coord_parents {
-        for (key, _) in clumps_map.iter() {
-            if coord.parent.clump_index > *key as i16 {
-                if coord.parent.clump_index > 0 {
-                    coord.parent.clump_index -= 1;
-                }
-                if coord.child.clump_index > 0 {
-                    coord.child.clump_index -= 1;
-                }
-            }
-        }
-    }
+    let dmg_coord_parents = remap.remap_coord_parents(&anm.coord_parents);
 
     // ----------------- Entries ----------------- //
-    let mut dmg_entries: Vec<AnmEntry> = anm.entries.clone();
-
-    for (key, _) in clumps_map.iter() {
-        dmg_entries.retain(|entry| entry.coord.clump_index != *key as i16);
-    }
-
-    for entry in &mut dmg_entries {
-        for (key, _) in clumps_map.iter() {
-            if entry.coord.clump_index > *key as i16 {
-                entry.coord.clump_index -= 1;
-            }
-        }
-    }
+    let mut dmg_entries = remap.remap_entries(&anm.entries);
 
     dmg_entries.retain(|entry| entry.coord.clump_index != -1);
     dmg_entries.sort_by(|a, b| {
@@ -965,8 +1367,8 @@ fn build_dmg_anm(
     let dmg_anm = NuccAnm {
         struct_info: struct_info.clone(),
         version: 121,
-        frame_count: anmstrm.frame_count - 100,
-        is_looped: anmstrm.is_looped,
+        frame_count: anm.frame_count,
+        is_looped: anm.is_looped,
         clumps: dmg_clumps,
         other_entries_indices: vec![],
         unk_entry_indices: vec![],
@@ -975,8 +1377,11 @@ fn build_dmg_anm(
     };
 
     // Remove the dmg entries from the main anm
-    anm.entries
-        .retain(|entry| entry.coord.clump_index != dmg_clump_index as i16);
+    anm.entries.retain(|entry| {
+        usize::try_from(entry.coord.clump_index).map_or(true, |clump_index| {
+            !dmg_clump_indices.contains(&clump_index)
+        })
+    });
 
     dmg_anm
 }
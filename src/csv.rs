@@ -0,0 +1,133 @@
+use std::fmt::Write;
+
+use xfbin::nucc::NuccStructReference;
+use xfbin::nucc_chunk::nucc_chunk_anmstrm::AnmStrmClump;
+use xfbin::nucc_chunk::nucc_chunk_anmstrmframe::Entry;
+use xfbin::Xfbin;
+
+use crate::converter::{build_entries_from_frames, find_anmstrm};
+use crate::error::StrmConvError;
+use crate::math::{normalize_quaternion, quaternion_to_euler};
+use crate::sample::FRAME_TICKS;
+
+const CSV_HEADER: &str = "frame,clump,coord,clump_name,bone_name,\
+location_x,location_y,location_z,\
+rotation_x,rotation_y,rotation_z,rotation_w,\
+euler_x,euler_y,euler_z,\
+scale_x,scale_y,scale_z,toggled";
+
+/// Options for [`export_bone_csv`].
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    /// Chunk name of the ANMSTRM to export, the first ANMSTRM when `None`.
+    pub chunk_name: Option<String>,
+    /// Number of keyframe ticks in one frame.
+    pub frame_ticks: i32,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            chunk_name: None,
+            frame_ticks: FRAME_TICKS,
+        }
+    }
+}
+
+/// Writes the bone transforms of an ANMSTRM as CSV, one row per frame, clump and coord.
+///
+/// The values are the ones stored in the ANMSTRM frames, before any conversion. Rotations are
+/// written both as a normalized quaternion and as Euler angles in degrees in Blender's XYZ
+/// order. Rows are ordered by frame, then clump, then coord.
+pub fn export_bone_csv(xfbin: &Xfbin, options: &CsvOptions) -> Result<String, StrmConvError> {
+    let chunk_name = match &options.chunk_name {
+        Some(chunk_name) => chunk_name.clone(),
+        None => crate::first_anmstrm_name(xfbin).ok_or(StrmConvError::NoAnmStrmChunk)?,
+    };
+
+    let (page_index, anmstrm, anmstrmframes) = find_anmstrm(xfbin, &chunk_name)?;
+    let struct_references = &xfbin.pages[page_index].struct_references;
+    let frame_ticks = options.frame_ticks.max(1);
+
+    let anmstrm_entries = build_entries_from_frames(anmstrmframes, &mut Vec::new(), true);
+
+    let mut rows = Vec::new();
+    for (tick, entry) in anmstrm_entries.iter().flatten() {
+        if let Entry::Bone(bone) = &entry.entry_data {
+            rows.push((tick / frame_ticks, entry.coord.clone(), bone));
+        }
+    }
+
+    rows.sort_by(|(frame_a, coord_a, _), (frame_b, coord_b, _)| {
+        frame_a
+            .cmp(frame_b)
+            .then_with(|| coord_a.clump_index.cmp(&coord_b.clump_index))
+            .then_with(|| coord_a.coord_index.cmp(&coord_b.coord_index))
+    });
+
+    let mut csv = String::from(CSV_HEADER);
+    csv.push('\n');
+
+    for (frame, coord, bone) in rows {
+        let clump = usize::try_from(coord.clump_index)
+            .ok()
+            .and_then(|clump_index| anmstrm.clumps.get(clump_index));
+
+        let rotation = normalize_quaternion(&bone.rotation);
+        let euler = quaternion_to_euler(&rotation);
+
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            frame,
+            coord.clump_index,
+            coord.coord_index,
+            escape(&clump_name(clump, struct_references)),
+            escape(&bone_name(clump, coord.coord_index, struct_references)),
+            bone.location.x,
+            bone.location.y,
+            bone.location.z,
+            rotation.x,
+            rotation.y,
+            rotation.z,
+            rotation.w,
+            euler.x,
+            euler.y,
+            euler.z,
+            bone.scale.x,
+            bone.scale.y,
+            bone.scale.z,
+            bone.toggled
+        );
+    }
+
+    Ok(csv)
+}
+
+fn clump_name(clump: Option<&AnmStrmClump>, struct_references: &[NuccStructReference]) -> String {
+    clump
+        .and_then(|clump| struct_references.get(clump.clump_index as usize))
+        .map(|reference| reference.struct_info.chunk_name.clone())
+        .unwrap_or_default()
+}
+
+fn bone_name(
+    clump: Option<&AnmStrmClump>,
+    coord_index: u16,
+    struct_references: &[NuccStructReference],
+) -> String {
+    clump
+        .and_then(|clump| clump.bone_material_indices.get(coord_index as usize))
+        .and_then(|&index| struct_references.get(index as usize))
+        .map(|reference| reference.struct_info.chunk_name.clone())
+        .unwrap_or_default()
+}
+
+/// Quotes a CSV field that contains a comma, quote or line break.
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,121 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use crate::trim::FrameRange;
+
+/// Errors that can stop a conversion.
+#[derive(Debug)]
+pub enum StrmConvError {
+    /// The xfbin doesn't contain any ANMSTRM chunk.
+    NoAnmStrmChunk,
+    /// The xfbin doesn't contain any ANM chunk.
+    NoAnmChunk,
+    /// No struct info with the given chunk name was found.
+    NoStructInfo { chunk_name: String },
+    /// A chunk couldn't be downcast to the struct its chunk type says it is.
+    DowncastFailed { chunk_type: String },
+    /// No clump matched the DMG clump selection.
+    NoDmgClumps,
+    /// A DMG clump index given with --dmg-index isn't a clump of the ANM.
+    DmgClumpIndexOutOfRange { index: usize, clump_count: usize },
+    /// The ANMSTRM has no frames inside the frame range.
+    EmptyFrameRange { range: FrameRange },
+    /// An ANMSTRM entry index doesn't fit in the u16 used by the ANM entries.
+    EntryIndexOverflow { index: usize },
+    /// The frame settings .xml couldn't be parsed.
+    XmlParse(quick_xml::Error),
+    /// A JSON description of an ANM doesn't describe a valid ANM.
+    InvalidAnmJson { chunk_name: String, message: String },
+    /// A JSON dump couldn't be written or read.
+    Json(serde_json::Error),
+    /// The xfbin couldn't be read or written.
+    Xfbin(String),
+    /// Reading or writing a file failed.
+    Io(io::Error),
+}
+
+impl fmt::Display for StrmConvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StrmConvError::NoAnmStrmChunk => {
+                write!(
+                    f,
+                    "the xfbin doesn't contain an ANMSTRM chunk, is it a strm .xfbin?"
+                )
+            }
+            StrmConvError::NoAnmChunk => {
+                write!(
+                    f,
+                    "the xfbin doesn't contain an ANM chunk, is it an anm .xfbin?"
+                )
+            }
+            StrmConvError::NoStructInfo { chunk_name } => {
+                write!(f, "no struct info for the chunk '{}' was found", chunk_name)
+            }
+            StrmConvError::DowncastFailed { chunk_type } => {
+                write!(
+                    f,
+                    "a {} chunk couldn't be read as its struct type",
+                    chunk_type
+                )
+            }
+            StrmConvError::EmptyFrameRange { range } => {
+                write!(f, "the ANMSTRM has no frames in the range {}", range)
+            }
+            StrmConvError::NoDmgClumps => {
+                write!(f, "no clump matched the DMG clump selection")
+            }
+            StrmConvError::DmgClumpIndexOutOfRange { index, clump_count } => write!(
+                f,
+                "DMG clump index {} is out of range, the ANM has {} clumps",
+                index, clump_count
+            ),
+            StrmConvError::EntryIndexOverflow { index } => write!(
+                f,
+                "entry index {} is too large for an ANM entry (max {})",
+                index,
+                u16::MAX
+            ),
+            StrmConvError::XmlParse(err) => {
+                write!(f, "failed to parse the frame settings .xml: {}", err)
+            }
+            StrmConvError::InvalidAnmJson {
+                chunk_name,
+                message,
+            } => write!(f, "invalid JSON for the ANM '{}': {}", chunk_name, message),
+            StrmConvError::Json(err) => write!(f, "JSON error: {}", err),
+            StrmConvError::Xfbin(message) => write!(f, "xfbin error: {}", message),
+            StrmConvError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for StrmConvError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StrmConvError::XmlParse(err) => Some(err),
+            StrmConvError::Json(err) => Some(err),
+            StrmConvError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for StrmConvError {
+    fn from(err: io::Error) -> Self {
+        StrmConvError::Io(err)
+    }
+}
+
+impl From<quick_xml::Error> for StrmConvError {
+    fn from(err: quick_xml::Error) -> Self {
+        StrmConvError::XmlParse(err)
+    }
+}
+
+impl From<serde_json::Error> for StrmConvError {
+    fn from(err: serde_json::Error) -> Self {
+        StrmConvError::Json(err)
+    }
+}
@@ -0,0 +1,652 @@
+use std::fs;
+use std::path::Path;
+
+use hashbrown::HashMap;
+use serde_json::{json, Value};
+use xfbin::nucc::nucc_anm::NuccAnm;
+use xfbin::nucc::NuccStructReference;
+use xfbin::nucc_chunk::nucc_chunk_anm::{AnmCoord, AnmEntry, AnmEntryFormat, Curve};
+use xfbin::nucc_chunk::nucc_helper::{Vector3, Vector4};
+
+use crate::error::StrmConvError;
+use crate::math::normalize_quaternion;
+use crate::sample::{sample_color, sample_float, sample_quaternion, sample_vector3, FRAME_TICKS};
+
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const GLB_JSON_CHUNK: u32 = 0x4E4F_534A;
+const GLB_BIN_CHUNK: u32 = 0x004E_4942;
+
+const FLOAT: u32 = 5126;
+
+/// Options for [`export_gltf`].
+#[derive(Debug, Clone)]
+pub struct GltfOptions {
+    /// Frames per second the animation is played back at.
+    pub fps: f32,
+    /// Number of keyframe ticks in one frame.
+    pub frame_ticks: i32,
+}
+
+impl Default for GltfOptions {
+    fn default() -> Self {
+        Self {
+            fps: 30.0,
+            frame_ticks: FRAME_TICKS,
+        }
+    }
+}
+
+/// A glTF 2.0 document and its binary buffer.
+pub struct GltfExport {
+    pub json: Value,
+    pub buffer: Vec<u8>,
+    /// Entries without a glTF equivalent (materials, ambient lights and morphs) that were left out.
+    pub skipped_entries: usize,
+}
+
+impl GltfExport {
+    /// Writes a .gltf with its buffer in a .bin next to it.
+    pub fn write_gltf(&self, path: &Path) -> Result<(), StrmConvError> {
+        let bin_path = path.with_extension("bin");
+        let bin_uri = bin_path
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let mut json = self.json.clone();
+        json["buffers"][0]["uri"] = Value::String(bin_uri);
+
+        fs::write(&bin_path, &self.buffer)?;
+        fs::write(path, serde_json::to_vec_pretty(&json).unwrap_or_default())?;
+
+        Ok(())
+    }
+
+    /// Writes a binary .glb with the buffer embedded.
+    pub fn write_glb(&self, path: &Path) -> Result<(), StrmConvError> {
+        fs::write(path, self.to_glb())?;
+
+        Ok(())
+    }
+
+    /// Writes a .glb when the path ends in .glb, otherwise a .gltf and .bin.
+    pub fn write(&self, path: &Path) -> Result<(), StrmConvError> {
+        let is_glb = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("glb"));
+
+        if is_glb {
+            self.write_glb(path)
+        } else {
+            self.write_gltf(path)
+        }
+    }
+
+    /// The document as a binary .glb.
+    pub fn to_glb(&self) -> Vec<u8> {
+        let mut json = serde_json::to_vec(&self.json).unwrap_or_default();
+        pad_to_four(&mut json, b' ');
+
+        let mut bin = self.buffer.clone();
+        pad_to_four(&mut bin, 0);
+
+        let length = 12 + 8 + json.len() + 8 + bin.len();
+
+        let mut glb = Vec::with_capacity(length);
+        glb.extend_from_slice(&GLB_MAGIC.to_le_bytes());
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&(length as u32).to_le_bytes());
+
+        glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(&GLB_JSON_CHUNK.to_le_bytes());
+        glb.extend_from_slice(&json);
+
+        glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        glb.extend_from_slice(&GLB_BIN_CHUNK.to_le_bytes());
+        glb.extend_from_slice(&bin);
+
+        glb
+    }
+}
+
+/// Exports ANMs to glTF 2.0 for previewing them outside the game.
+///
+/// Each ANM is given with the struct references of its own page, so the main and DMG ANMs of
+/// a converted cutscene can be exported together, each with its own animations. Every clump
+/// becomes a node with its bones as children, following the coord parents, and gets its own
+/// animation with translation, rotation and scale samplers for every bone.
+/// Cameras become camera nodes with an animated yfov and directional and point lights become
+/// `KHR_lights_punctual` lights, both animated in a separate `scene` animation. The yfov and
+/// light colors are animated through `KHR_animation_pointer`. Every curve is sampled once
+/// per frame and values are kept in game units.
+pub fn export_gltf(
+    anms: &[(&NuccAnm, &[NuccStructReference])],
+    options: &GltfOptions,
+) -> GltfExport {
+    let mut builder = GltfBuilder::new(anms, options);
+
+    for (anm_index, (anm, _)) in anms.iter().enumerate() {
+        for entry in &anm.entries {
+            builder.add_entry(anm_index, entry);
+        }
+    }
+
+    builder.finish()
+}
+
+/// Index of the ANM, clump index and coord index of a node, the coord is `None` for clumps.
+type NodeKey = (usize, i16, Option<u16>);
+
+/// Index of the ANM and name of an animation, so the DMG ANM's animations stay apart.
+type AnimationKey = (usize, String);
+
+struct GltfBuilder<'a> {
+    anms: &'a [(&'a NuccAnm, &'a [NuccStructReference])],
+    frame_ticks: i32,
+    frame_count: i32,
+
+    buffer: Vec<u8>,
+    buffer_views: Vec<Value>,
+    accessors: Vec<Value>,
+    nodes: Vec<Value>,
+    cameras: Vec<Value>,
+    lights: Vec<Value>,
+
+    /// Node of every clump and coord, by ANM, clump index and coord index (`None` for the clump).
+    node_indices: HashMap<NodeKey, usize>,
+    /// Samplers and channels of every animation, by ANM and name.
+    animations: Vec<(AnimationKey, Vec<Value>, Vec<Value>)>,
+    times_accessor: usize,
+    /// Whether a channel animates a property through `KHR_animation_pointer`.
+    uses_animation_pointer: bool,
+    skipped_entries: usize,
+}
+
+impl<'a> GltfBuilder<'a> {
+    fn new(anms: &'a [(&'a NuccAnm, &'a [NuccStructReference])], options: &GltfOptions) -> Self {
+        let frame_ticks = options.frame_ticks.max(1);
+        let frame_count = anms
+            .iter()
+            .map(|(anm, _)| anm.frame_count as i32 / frame_ticks + 1)
+            .max()
+            .unwrap_or(1);
+
+        let mut builder = Self {
+            anms,
+            frame_ticks,
+            frame_count,
+            buffer: Vec::new(),
+            buffer_views: Vec::new(),
+            accessors: Vec::new(),
+            nodes: Vec::new(),
+            cameras: Vec::new(),
+            lights: Vec::new(),
+            node_indices: HashMap::new(),
+            animations: Vec::new(),
+            times_accessor: 0,
+            uses_animation_pointer: false,
+            skipped_entries: 0,
+        };
+
+        let times = (0..frame_count)
+            .map(|frame| frame as f32 / options.fps)
+            .collect::<Vec<_>>();
+        builder.times_accessor = builder.add_accessor(&times, "SCALAR", true);
+
+        builder
+    }
+
+    fn add_entry(&mut self, anm_index: usize, entry: &AnmEntry) {
+        let entry_format = entry.entry_format;
+        let node = self.coord_node(anm_index, &entry.coord);
+        let animation = self.animation_key(anm_index, &entry.coord);
+
+        if entry_format == AnmEntryFormat::BONE as u16 {
+            self.add_vector3_channel(&animation, node, "translation", entry.curves.first());
+            self.add_rotation_channel(&animation, node, entry.curves.get(1));
+            self.add_vector3_channel(&animation, node, "scale", entry.curves.get(2));
+        } else if entry_format == AnmEntryFormat::CAMERA as u16 {
+            let camera = self.cameras.len();
+            let yfov = entry
+                .curves
+                .get(2)
+                .and_then(|curve| sample_float(curve, 0, self.frame_ticks))
+                .unwrap_or(45.0)
+                .to_radians();
+
+            self.cameras.push(json!({
+                "type": "perspective",
+                "perspective": { "yfov": yfov, "znear": 0.1 },
+            }));
+            self.nodes[node]["camera"] = json!(camera);
+
+            self.add_vector3_channel(&animation, node, "translation", entry.curves.first());
+            self.add_rotation_channel(&animation, node, entry.curves.get(1));
+
+            if let Some(curve) = entry.curves.get(2) {
+                let yfov = self.sample_frames(|tick| {
+                    vec![sample_float(curve, tick, self.frame_ticks)
+                        .unwrap_or_default()
+                        .to_radians()]
+                });
+                self.add_pointer_channel(
+                    &animation,
+                    &format!("/cameras/{}/perspective/yfov", camera),
+                    &yfov,
+                    "SCALAR",
+                );
+            }
+        } else if entry_format == AnmEntryFormat::LIGHTDIRC as u16
+            || entry_format == AnmEntryFormat::LIGHTPOINT as u16
+        {
+            let is_point = entry_format == AnmEntryFormat::LIGHTPOINT as u16;
+            let light = self.lights.len();
+
+            let mut light_json = json!({
+                "type": if is_point { "point" } else { "directional" },
+                "color": self.first_color(entry.curves.first()),
+                "intensity": entry
+                    .curves
+                    .get(1)
+                    .and_then(|curve| sample_float(curve, 0, self.frame_ticks))
+                    .unwrap_or(1.0),
+            });
+            if is_point {
+                if let Some(range) = entry
+                    .curves
+                    .get(3)
+                    .and_then(|curve| sample_float(curve, 0, self.frame_ticks))
+                {
+                    if range > 0.0 {
+                        light_json["range"] = json!(range);
+                    }
+                }
+            }
+
+            self.lights.push(light_json);
+            self.nodes[node]["extensions"] = json!({ "KHR_lights_punctual": { "light": light } });
+
+            if is_point {
+                self.add_vector3_channel(&animation, node, "translation", entry.curves.get(2));
+            } else {
+                self.add_rotation_channel(&animation, node, entry.curves.get(2));
+            }
+
+            if let Some(curve) = entry.curves.first() {
+                let color = self.sample_frames(|tick| {
+                    let color = sample_color(curve, tick, self.frame_ticks).unwrap_or_default();
+                    vec![color.x, color.y, color.z]
+                });
+                self.add_pointer_channel(
+                    &animation,
+                    &format!("/extensions/KHR_lights_punctual/lights/{}/color", light),
+                    &color,
+                    "VEC3",
+                );
+            }
+
+            if let Some(curve) = entry.curves.get(1) {
+                let intensity = self.sample_frames(|tick| {
+                    vec![sample_float(curve, tick, self.frame_ticks).unwrap_or_default()]
+                });
+                self.add_pointer_channel(
+                    &animation,
+                    &format!("/extensions/KHR_lights_punctual/lights/{}/intensity", light),
+                    &intensity,
+                    "SCALAR",
+                );
+            }
+        } else {
+            self.skipped_entries += 1;
+        }
+    }
+
+    fn finish(mut self) -> GltfExport {
+        // Parent the coords like the coord parents, the clump nodes hold the rest of their bones
+        let mut parents: HashMap<usize, usize> = HashMap::new();
+        let coord_parents = self
+            .anms
+            .iter()
+            .enumerate()
+            .flat_map(|(anm_index, (anm, _))| {
+                anm.coord_parents
+                    .iter()
+                    .map(move |coord_parent| (anm_index, coord_parent))
+            });
+        for (anm_index, coord_parent) in coord_parents {
+            let (parent, child) = match (
+                self.node_indices
+                    .get(&coord_key(anm_index, &coord_parent.parent)),
+                self.node_indices
+                    .get(&coord_key(anm_index, &coord_parent.child)),
+            ) {
+                (Some(parent), Some(child)) => (*parent, *child),
+                _ => continue,
+            };
+
+            if parent != child {
+                parents.entry(child).or_insert(parent);
+            }
+        }
+
+        let coord_nodes = self
+            .node_indices
+            .iter()
+            .filter_map(|((anm_index, clump_index, coord_index), node)| {
+                coord_index.map(|_| (*anm_index, *clump_index, *node))
+            })
+            .collect::<Vec<_>>();
+
+        for (anm_index, clump_index, node) in coord_nodes {
+            if parents.contains_key(&node) || clump_index < 0 {
+                continue;
+            }
+
+            if let Some(clump_node) = self.node_indices.get(&(anm_index, clump_index, None)) {
+                parents.insert(node, *clump_node);
+            }
+        }
+
+        let mut children: Vec<Vec<usize>> = vec![Vec::new(); self.nodes.len()];
+        for (child, parent) in &parents {
+            children[*parent].push(*child);
+        }
+
+        let mut roots = Vec::new();
+        for (node, node_children) in children.iter_mut().enumerate() {
+            if !node_children.is_empty() {
+                node_children.sort_unstable();
+                self.nodes[node]["children"] = json!(node_children);
+            }
+
+            if !parents.contains_key(&node) {
+                roots.push(node);
+            }
+        }
+
+        let animations = self
+            .animations
+            .into_iter()
+            .map(|((_, name), samplers, channels)| {
+                json!({ "name": name, "samplers": samplers, "channels": channels })
+            })
+            .collect::<Vec<_>>();
+
+        let mut extensions_used = Vec::new();
+        if self.uses_animation_pointer {
+            extensions_used.push("KHR_animation_pointer");
+        }
+
+        let scene_name = self
+            .anms
+            .first()
+            .map(|(anm, _)| anm.struct_info.chunk_name.clone())
+            .unwrap_or_default();
+
+        let mut json = json!({
+            "asset": { "version": "2.0", "generator": "strmconv" },
+            "scene": 0,
+            "scenes": [{ "name": scene_name, "nodes": roots }],
+            "nodes": self.nodes,
+            "animations": animations,
+            "accessors": self.accessors,
+            "bufferViews": self.buffer_views,
+            "buffers": [{ "byteLength": self.buffer.len() }],
+        });
+
+        if !self.cameras.is_empty() {
+            json["cameras"] = json!(self.cameras);
+        }
+
+        if !self.lights.is_empty() {
+            extensions_used.push("KHR_lights_punctual");
+            json["extensions"] = json!({ "KHR_lights_punctual": { "lights": self.lights } });
+        }
+
+        if !extensions_used.is_empty() {
+            json["extensionsUsed"] = json!(extensions_used);
+        }
+
+        GltfExport {
+            json,
+            buffer: self.buffer,
+            skipped_entries: self.skipped_entries,
+        }
+    }
+
+    /// Returns the node of a coord, creating it and the node of its clump if needed.
+    fn coord_node(&mut self, anm_index: usize, coord: &AnmCoord) -> usize {
+        let key = coord_key(anm_index, coord);
+        if let Some(node) = self.node_indices.get(&key) {
+            return *node;
+        }
+
+        let clump_key = (anm_index, coord.clump_index, None);
+        if coord.clump_index >= 0 && !self.node_indices.contains_key(&clump_key) {
+            let name = self.clump_name(anm_index, coord.clump_index);
+            self.node_indices.insert(clump_key, self.nodes.len());
+            self.nodes.push(json!({ "name": name }));
+        }
+
+        let name = self
+            .coord_reference(anm_index, coord)
+            .map(|reference| reference.struct_info.chunk_name.clone())
+            .unwrap_or_else(|| format!("coord_{}_{}", coord.clump_index, coord.coord_index));
+
+        let node = self.nodes.len();
+        self.node_indices.insert(key, node);
+        self.nodes.push(json!({ "name": name }));
+
+        node
+    }
+
+    /// The animation a coord is animated in, its clump's or `scene` for cameras and lights.
+    fn animation_key(&self, anm_index: usize, coord: &AnmCoord) -> AnimationKey {
+        let name = if coord.clump_index < 0 {
+            "scene".to_string()
+        } else {
+            self.clump_name(anm_index, coord.clump_index)
+        };
+
+        (anm_index, name)
+    }
+
+    fn clump_name(&self, anm_index: usize, clump_index: i16) -> String {
+        let (anm, struct_references) = self.anms[anm_index];
+
+        anm.clumps
+            .get(clump_index as usize)
+            .and_then(|clump| struct_references.get(clump.clump_index as usize))
+            .map(|reference| reference.struct_info.chunk_name.clone())
+            .unwrap_or_else(|| format!("clump_{}", clump_index))
+    }
+
+    /// The struct reference of the bone, material or other entry a coord animates.
+    fn coord_reference(
+        &self,
+        anm_index: usize,
+        coord: &AnmCoord,
+    ) -> Option<&'a NuccStructReference> {
+        let (anm, struct_references) = self.anms[anm_index];
+
+        let index = if coord.clump_index < 0 {
+            *anm.other_entries_indices.get(coord.coord_index as usize)?
+        } else {
+            *anm.clumps
+                .get(coord.clump_index as usize)?
+                .bone_material_indices
+                .get(coord.coord_index as usize)?
+        };
+
+        struct_references.get(index as usize)
+    }
+
+    fn first_color(&self, curve: Option<&Curve>) -> Value {
+        let color = curve
+            .and_then(|curve| sample_color(curve, 0, self.frame_ticks))
+            .unwrap_or(Vector3 {
+                x: 1.0,
+                y: 1.0,
+                z: 1.0,
+            });
+
+        json!([color.x, color.y, color.z])
+    }
+
+    /// Samples a value at every frame and flattens it.
+    fn sample_frames(&self, sample: impl Fn(i32) -> Vec<f32>) -> Vec<f32> {
+        (0..self.frame_count)
+            .flat_map(|frame| sample(frame * self.frame_ticks))
+            .collect()
+    }
+
+    fn add_vector3_channel(
+        &mut self,
+        animation: &AnimationKey,
+        node: usize,
+        path: &str,
+        curve: Option<&Curve>,
+    ) {
+        let curve = match curve {
+            Some(curve) if sample_vector3(curve, 0, self.frame_ticks).is_some() => curve,
+            _ => return,
+        };
+
+        let values = self.sample_frames(|tick| {
+            let value = sample_vector3(curve, tick, self.frame_ticks).unwrap_or_default();
+            vec![value.x, value.y, value.z]
+        });
+
+        self.add_channel(
+            animation,
+            json!({ "node": node, "path": path }),
+            &values,
+            "VEC3",
+        );
+    }
+
+    fn add_rotation_channel(
+        &mut self,
+        animation: &AnimationKey,
+        node: usize,
+        curve: Option<&Curve>,
+    ) {
+        let curve = match curve {
+            Some(curve @ (Curve::QuaternionShort(_) | Curve::KeyframeVector4(_))) => curve,
+            _ => return,
+        };
+
+        let values = self.sample_frames(|tick| {
+            let rotation = sample_quaternion(curve, tick, self.frame_ticks)
+                .map(|rotation| normalize_quaternion(&rotation))
+                .unwrap_or(Vector4 {
+                    x: 0.0,
+                    y: 0.0,
+                    z: 0.0,
+                    w: 1.0,
+                });
+            vec![rotation.x, rotation.y, rotation.z, rotation.w]
+        });
+
+        self.add_channel(
+            animation,
+            json!({ "node": node, "path": "rotation" }),
+            &values,
+            "VEC4",
+        );
+    }
+
+    /// Adds a channel that animates any property through `KHR_animation_pointer`.
+    fn add_pointer_channel(
+        &mut self,
+        animation: &AnimationKey,
+        pointer: &str,
+        values: &[f32],
+        accessor_type: &str,
+    ) {
+        let target = json!({
+            "path": "pointer",
+            "extensions": { "KHR_animation_pointer": { "pointer": pointer } },
+        });
+        self.uses_animation_pointer = true;
+
+        self.add_channel(animation, target, values, accessor_type);
+    }
+
+    fn add_channel(
+        &mut self,
+        animation: &AnimationKey,
+        target: Value,
+        values: &[f32],
+        accessor_type: &str,
+    ) {
+        let output = self.add_accessor(values, accessor_type, false);
+
+        let index = match self
+            .animations
+            .iter()
+            .position(|(key, _, _)| key == animation)
+        {
+            Some(index) => index,
+            None => {
+                self.animations
+                    .push((animation.clone(), Vec::new(), Vec::new()));
+                self.animations.len() - 1
+            }
+        };
+
+        let (_, samplers, channels) = &mut self.animations[index];
+        channels.push(json!({ "sampler": samplers.len(), "target": target }));
+        samplers.push(json!({
+            "input": self.times_accessor,
+            "output": output,
+            "interpolation": "LINEAR",
+        }));
+    }
+
+    /// Appends float data to the buffer and returns the index of its accessor.
+    fn add_accessor(&mut self, values: &[f32], accessor_type: &str, min_max: bool) -> usize {
+        let components = match accessor_type {
+            "VEC3" => 3,
+            "VEC4" => 4,
+            _ => 1,
+        };
+
+        let byte_offset = self.buffer.len();
+        for value in values {
+            self.buffer.extend_from_slice(&value.to_le_bytes());
+        }
+
+        self.buffer_views.push(json!({
+            "buffer": 0,
+            "byteOffset": byte_offset,
+            "byteLength": values.len() * 4,
+        }));
+
+        let mut accessor = json!({
+            "bufferView": self.buffer_views.len() - 1,
+            "componentType": FLOAT,
+            "count": values.len() / components,
+            "type": accessor_type,
+        });
+
+        if min_max {
+            let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+            let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+            accessor["min"] = json!([min]);
+            accessor["max"] = json!([max]);
+        }
+
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+}
+
+/// The node key of a coord of the ANM at `anm_index`.
+fn coord_key(anm_index: usize, coord: &AnmCoord) -> NodeKey {
+    (anm_index, coord.clump_index, Some(coord.coord_index))
+}
+
+fn pad_to_four(bytes: &mut Vec<u8>, padding: u8) {
+    bytes.resize(bytes.len().next_multiple_of(4), padding);
+}
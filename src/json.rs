@@ -0,0 +1,994 @@
+use serde::{Deserialize, Serialize};
+use xfbin::nucc::nucc_anm::NuccAnm;
+use xfbin::nucc::nucc_anmstrm::NuccAnmStrm;
+use xfbin::nucc::nucc_anmstrmframe::NuccAnmStrmFrame;
+use xfbin::nucc::{NuccStruct, NuccStructInfo, NuccStructReference};
+use xfbin::nucc_chunk::nucc_chunk_anm::{
+    AnmClump, AnmCoord, AnmEntry, AnmEntryFormat, CoordParent, Curve, CurveHeader,
+};
+use xfbin::nucc_chunk::nucc_chunk_anmstrmframe::{AnmStrmEntry, Entry};
+use xfbin::nucc_chunk::nucc_helper::{
+    KeyframeFloat, KeyframeVector3, KeyframeVector4, QuaternionShort, Vector3, Vector4, RGB,
+};
+use xfbin::nucc_chunk::NuccChunkType;
+use xfbin::{xfbin::XfbinPage, Xfbin};
+
+use crate::converter::finish_curves;
+use crate::error::StrmConvError;
+
+/// Every ANMSTRM and ANM of an xfbin, in a form that serialises to readable JSON.
+///
+/// Struct reference indices are resolved to the chunks they point at, so two dumps can be
+/// diffed even when the pages list their references in a different order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct XfbinDump {
+    #[serde(default)]
+    pub anmstrms: Vec<AnmStrmDump>,
+    pub anms: Vec<AnmDump>,
+}
+
+/// The header of an ANMSTRM with the values of every entry in every one of its frames.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnmStrmDump {
+    pub chunk_name: String,
+    pub filepath: String,
+    pub version: u16,
+    pub frame_count: u32,
+    pub frame_size: u32,
+    pub is_looped: bool,
+    pub clumps: Vec<ClumpDump>,
+    pub other_entries: Vec<ReferenceDump>,
+    pub coord_parents: Vec<CoordParentDump>,
+    pub frames: Vec<FrameDump>,
+}
+
+/// An ANM with the keys and headers of every curve.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnmDump {
+    pub chunk_name: String,
+    #[serde(default)]
+    pub filepath: String,
+    #[serde(default = "default_anm_version")]
+    pub version: u16,
+    pub frame_count: u32,
+    #[serde(default)]
+    pub is_looped: bool,
+    pub clumps: Vec<ClumpDump>,
+    #[serde(default)]
+    pub other_entries: Vec<ReferenceDump>,
+    #[serde(default)]
+    pub unk_entry_indices: Vec<u32>,
+    #[serde(default)]
+    pub coord_parents: Vec<CoordParentDump>,
+    pub entries: Vec<AnmEntryDump>,
+}
+
+/// The chunk a struct reference points at.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReferenceDump {
+    /// Name of the reference, usually the same as the chunk name.
+    pub name: String,
+    pub chunk_name: String,
+    pub chunk_type: String,
+    #[serde(default)]
+    pub filepath: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClumpDump {
+    pub clump: ReferenceDump,
+    /// Bones and materials, in coord index order.
+    pub bone_materials: Vec<ReferenceDump>,
+    #[serde(default)]
+    pub models: Vec<ReferenceDump>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct CoordDump {
+    pub clump_index: i16,
+    pub coord_index: u16,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct CoordParentDump {
+    pub parent: CoordDump,
+    pub child: CoordDump,
+}
+
+/// The entries of one ANMSTRMFRAME.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrameDump {
+    /// Tick of the frame, 100 ticks per frame unless the ANMSTRM uses another unit.
+    pub frame: u32,
+    pub entries: Vec<StrmEntryDump>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrmEntryDump {
+    pub coord: CoordDump,
+    pub entry_format: u16,
+    #[serde(flatten)]
+    pub data: StrmEntryDataDump,
+}
+
+/// The values of an ANMSTRM entry in one frame.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StrmEntryDataDump {
+    Bone {
+        location: [f32; 3],
+        rotation: [f32; 4],
+        scale: [f32; 3],
+        toggled: f32,
+    },
+    Material {
+        ambient_color: Vec<f32>,
+    },
+    Camera {
+        location: [f32; 3],
+        rotation: [f32; 4],
+        fov: f32,
+    },
+    LightDirc {
+        color: [f32; 3],
+        intensity: f32,
+        direction: [f32; 4],
+    },
+    LightPoint {
+        color: [f32; 3],
+        intensity: f32,
+        position: [f32; 3],
+        radius: f32,
+        falloff: f32,
+    },
+    Ambient {
+        color: [f32; 3],
+        intensity: f32,
+    },
+    MorphModel {
+        frame_count: u32,
+        morph_weight: Vec<f32>,
+    },
+    Unknown {
+        bytes: Vec<u8>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnmEntryDump {
+    pub coord: CoordDump,
+    pub entry_format: u16,
+    pub curves: Vec<CurveDump>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CurveDump {
+    /// Only the curve format is read back, the rest is worked out from the keys.
+    #[serde(default)]
+    pub header: CurveHeaderDump,
+    #[serde(flatten)]
+    pub keys: CurveKeysDump,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct CurveHeaderDump {
+    pub curve_index: u16,
+    pub curve_format: u16,
+    pub frame_count: u16,
+    pub curve_size: u16,
+}
+
+/// The keys of a curve, tagged with the curve's type.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "keys", rename_all = "snake_case")]
+pub enum CurveKeysDump {
+    KeyframeVector3(Vec<KeyframeVector3Dump>),
+    KeyframeVector4(Vec<KeyframeVector4Dump>),
+    QuaternionShort(Vec<[i16; 4]>),
+    Float(Vec<f32>),
+    KeyframeFloat(Vec<KeyframeFloatDump>),
+    Rgb(Vec<[u8; 3]>),
+    Vector3(Vec<[f32; 3]>),
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct KeyframeVector3Dump {
+    pub frame: i32,
+    pub value: [f32; 3],
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct KeyframeVector4Dump {
+    pub frame: i32,
+    pub value: [f32; 4],
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct KeyframeFloatDump {
+    pub frame: i32,
+    pub value: f32,
+}
+
+/// Dumps every ANMSTRM and ANM of an xfbin.
+///
+/// With `include_frames` off, the ANMSTRMs only get their header, clumps and coord parents,
+/// which keeps the dump of a long cutscene small.
+pub fn dump_xfbin(xfbin: &Xfbin, include_frames: bool) -> Result<XfbinDump, StrmConvError> {
+    let mut anmstrm_dumps = Vec::new();
+    let mut anm_dumps = Vec::new();
+    let mut anmstrmframes = Vec::new();
+
+    for page in &xfbin.pages {
+        for nucc_struct in &page.structs {
+            match nucc_struct.chunk_type() {
+                NuccChunkType::NuccChunkAnmStrm => {
+                    let anmstrm = nucc_struct.downcast_ref::<NuccAnmStrm>().ok_or_else(|| {
+                        StrmConvError::DowncastFailed {
+                            chunk_type: NuccChunkType::NuccChunkAnmStrm.to_string(),
+                        }
+                    })?;
+
+                    anmstrm_dumps.push(dump_anmstrm(anmstrm, &page.struct_references));
+                }
+                NuccChunkType::NuccChunkAnmStrmFrame if include_frames => {
+                    let anmstrmframe =
+                        nucc_struct
+                            .downcast_ref::<NuccAnmStrmFrame>()
+                            .ok_or_else(|| StrmConvError::DowncastFailed {
+                                chunk_type: NuccChunkType::NuccChunkAnmStrmFrame.to_string(),
+                            })?;
+
+                    anmstrmframes.push(anmstrmframe);
+                }
+                NuccChunkType::NuccChunkAnm => {
+                    let anm = nucc_struct.downcast_ref::<NuccAnm>().ok_or_else(|| {
+                        StrmConvError::DowncastFailed {
+                            chunk_type: NuccChunkType::NuccChunkAnm.to_string(),
+                        }
+                    })?;
+
+                    anm_dumps.push(dump_anm(anm, &page.struct_references));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Frames can be on any page, they belong to the ANMSTRM with their chunk name
+    anmstrmframes.sort_by_key(|anmstrmframe| anmstrmframe.frame);
+    for anmstrmframe in anmstrmframes {
+        if let Some(anmstrm_dump) = anmstrm_dumps
+            .iter_mut()
+            .find(|dump| dump.chunk_name == anmstrmframe.struct_info.chunk_name)
+        {
+            anmstrm_dump.frames.push(FrameDump {
+                frame: anmstrmframe.frame,
+                entries: anmstrmframe.entries.iter().map(dump_strm_entry).collect(),
+            });
+        }
+    }
+
+    Ok(XfbinDump {
+        anmstrms: anmstrm_dumps,
+        anms: anm_dumps,
+    })
+}
+
+/// Dumps the header, clumps and coord parents of an ANMSTRM, without its frames.
+pub fn dump_anmstrm(
+    anmstrm: &NuccAnmStrm,
+    struct_references: &[NuccStructReference],
+) -> AnmStrmDump {
+    AnmStrmDump {
+        chunk_name: anmstrm.struct_info.chunk_name.clone(),
+        filepath: anmstrm.struct_info.filepath.clone(),
+        version: anmstrm.version,
+        frame_count: anmstrm.frame_count,
+        frame_size: anmstrm.frame_size,
+        is_looped: anmstrm.is_looped,
+        clumps: anmstrm
+            .clumps
+            .iter()
+            .map(|clump| {
+                dump_clump(
+                    clump.clump_index,
+                    &clump.bone_material_indices,
+                    &clump.model_indices,
+                    struct_references,
+                )
+            })
+            .collect(),
+        other_entries: dump_references(&anmstrm.other_entry_indices, struct_references),
+        coord_parents: anmstrm
+            .coord_parents
+            .iter()
+            .map(dump_coord_parent)
+            .collect(),
+        frames: Vec::new(),
+    }
+}
+
+/// Dumps an ANM with its curves and curve headers.
+pub fn dump_anm(anm: &NuccAnm, struct_references: &[NuccStructReference]) -> AnmDump {
+    AnmDump {
+        chunk_name: anm.struct_info.chunk_name.clone(),
+        filepath: anm.struct_info.filepath.clone(),
+        version: anm.version,
+        frame_count: anm.frame_count,
+        is_looped: anm.is_looped,
+        clumps: anm
+            .clumps
+            .iter()
+            .map(|clump| {
+                dump_clump(
+                    clump.clump_index,
+                    &clump.bone_material_indices,
+                    &clump.model_indices,
+                    struct_references,
+                )
+            })
+            .collect(),
+        other_entries: dump_references(&anm.other_entries_indices, struct_references),
+        unk_entry_indices: anm.unk_entry_indices.clone(),
+        coord_parents: anm.coord_parents.iter().map(dump_coord_parent).collect(),
+        entries: anm.entries.iter().map(dump_anm_entry).collect(),
+    }
+}
+
+/// An anm xfbin built from JSON.
+pub struct ImportOutput {
+    /// Chunk name of the first ANM, used for the output file name.
+    pub chunk_name: String,
+    pub xfbin: Xfbin,
+}
+
+/// Builds an anm xfbin from JSON, either a whole dump or a single ANM.
+///
+/// Every ANM gets its own page with the struct references it points at. Only the ANMs are
+/// built, the cameras and lights they point at aren't part of the JSON. `frame_ticks` is the
+/// number of ticks between two frames of the per-frame curves.
+pub fn import_anm_json(json: &str, frame_ticks: i32) -> Result<ImportOutput, StrmConvError> {
+    let value: serde_json::Value = serde_json::from_str(json)?;
+
+    let anm_dumps = if value.get("anms").is_some() {
+        serde_json::from_value::<XfbinDump>(value)?.anms
+    } else {
+        vec![serde_json::from_value::<AnmDump>(value)?]
+    };
+
+    let chunk_name = anm_dumps
+        .first()
+        .map(|anm_dump| anm_dump.chunk_name.clone())
+        .ok_or(StrmConvError::NoAnmChunk)?;
+
+    let mut xfbin = Xfbin::default();
+    for anm_dump in &anm_dumps {
+        xfbin.pages.push(build_anm_page(anm_dump, frame_ticks)?);
+    }
+
+    Ok(ImportOutput { chunk_name, xfbin })
+}
+
+/// Validates an ANM description and builds a page with the ANM and its struct references.
+///
+/// The curve headers are worked out from the keys, and keyed curves get their null keyframe
+/// appended like converted ones. A dumped curve that already ends in one keeps only one.
+pub fn build_anm_page(anm_dump: &AnmDump, frame_ticks: i32) -> Result<XfbinPage, StrmConvError> {
+    validate_anm_dump(anm_dump, frame_ticks)?;
+
+    let struct_info = NuccStructInfo {
+        chunk_name: anm_dump.chunk_name.clone(),
+        chunk_type: NuccChunkType::NuccChunkAnm.to_string(),
+        filepath: anm_dump.filepath.clone(),
+    };
+
+    let mut struct_infos = vec![struct_info.clone()];
+    let mut struct_references: Vec<NuccStructReference> = Vec::new();
+
+    let mut reference_index = |reference: &ReferenceDump| {
+        let reference_info = NuccStructInfo {
+            chunk_name: reference.chunk_name.clone(),
+            chunk_type: reference.chunk_type.clone(),
+            filepath: reference.filepath.clone(),
+        };
+
+        if !struct_infos.contains(&reference_info) {
+            struct_infos.push(reference_info.clone());
+        }
+
+        let index = match struct_references.iter().position(|struct_reference| {
+            struct_reference.chunk_name == reference.name
+                && struct_reference.struct_info == reference_info
+        }) {
+            Some(index) => index,
+            None => {
+                struct_references.push(NuccStructReference {
+                    chunk_name: reference.name.clone(),
+                    struct_info: reference_info,
+                });
+                struct_references.len() - 1
+            }
+        };
+
+        index as u32
+    };
+
+    let clumps = anm_dump
+        .clumps
+        .iter()
+        .map(|clump| AnmClump {
+            clump_index: reference_index(&clump.clump),
+            bone_material_indices: clump
+                .bone_materials
+                .iter()
+                .map(&mut reference_index)
+                .collect(),
+            model_indices: clump.models.iter().map(&mut reference_index).collect(),
+        })
+        .collect();
+
+    let other_entries_indices = anm_dump
+        .other_entries
+        .iter()
+        .map(&mut reference_index)
+        .collect();
+
+    let mut entries = anm_dump
+        .entries
+        .iter()
+        .map(|entry_dump| {
+            let mut anm_entry = AnmEntry {
+                coord: anm_coord(&entry_dump.coord),
+                entry_format: entry_dump.entry_format,
+                curve_count: 0,
+                curve_headers: Vec::new(),
+                curves: Vec::new(),
+            };
+
+            for (i, curve_dump) in entry_dump.curves.iter().enumerate() {
+                let curve = import_curve_keys(&curve_dump.keys);
+
+                anm_entry.curve_headers.push(CurveHeader {
+                    curve_index: i as u16,
+                    curve_format: match curve_dump.header.curve_format {
+                        0 => curve.get_curve_format(),
+                        curve_format => curve_format,
+                    },
+                    frame_count: 0,
+                    curve_size: 0,
+                });
+                anm_entry.curves.push(curve);
+            }
+
+            finish_curves(&mut anm_entry);
+            anm_entry
+        })
+        .collect::<Vec<_>>();
+
+    entries.sort_by(|a, b| {
+        a.coord
+            .clump_index
+            .cmp(&b.coord.clump_index)
+            .then_with(|| a.coord.coord_index.cmp(&b.coord.coord_index))
+            .then_with(|| a.entry_format.cmp(&b.entry_format))
+    });
+
+    let anm = NuccAnm {
+        struct_info,
+        version: anm_dump.version,
+        frame_count: anm_dump.frame_count,
+        is_looped: anm_dump.is_looped,
+        clumps,
+        other_entries_indices,
+        unk_entry_indices: anm_dump.unk_entry_indices.clone(),
+        coord_parents: anm_dump
+            .coord_parents
+            .iter()
+            .map(|coord_parent| CoordParent {
+                parent: anm_coord(&coord_parent.parent),
+                child: anm_coord(&coord_parent.child),
+            })
+            .collect(),
+        entries,
+    };
+
+    Ok(XfbinPage {
+        struct_infos,
+        struct_references,
+        structs: vec![Box::new(anm) as Box<dyn NuccStruct>],
+    })
+}
+
+/// Checks that every coord points at a bone, material or other entry, that no coord is
+/// animated twice with the same entry format and that the keys are in order and inside the ANM.
+///
+/// Keyed material curves may have one key half a frame past the end, where the converter puts
+/// the last color key. Per-frame curves need a value for every frame, or a single value when
+/// they're constant.
+fn validate_anm_dump(anm_dump: &AnmDump, frame_ticks: i32) -> Result<(), StrmConvError> {
+    let invalid = |message: String| StrmConvError::InvalidAnmJson {
+        chunk_name: anm_dump.chunk_name.clone(),
+        message,
+    };
+
+    if anm_dump.chunk_name.is_empty() {
+        return Err(invalid("the chunk name is empty".to_string()));
+    }
+
+    let check_coord = |coord: &CoordDump| {
+        let coord_count = if coord.clump_index < 0 {
+            anm_dump.other_entries.len()
+        } else {
+            anm_dump
+                .clumps
+                .get(coord.clump_index as usize)
+                .map_or(0, |clump| clump.bone_materials.len())
+        };
+
+        if (coord.coord_index as usize) < coord_count {
+            Ok(())
+        } else {
+            Err(invalid(format!(
+                "the coord {}:{} doesn't point at a bone, material or other entry",
+                coord.clump_index, coord.coord_index
+            )))
+        }
+    };
+
+    for coord_parent in &anm_dump.coord_parents {
+        check_coord(&coord_parent.parent)?;
+        check_coord(&coord_parent.child)?;
+    }
+
+    let frame_ticks = frame_ticks.max(1);
+    let last_tick = i32::try_from(anm_dump.frame_count).unwrap_or(i32::MAX);
+    let frame_values = (anm_dump.frame_count / frame_ticks as u32) as usize + 1;
+
+    for (i, entry) in anm_dump.entries.iter().enumerate() {
+        check_coord(&entry.coord)?;
+
+        let is_duplicate = anm_dump.entries[..i].iter().any(|other| {
+            other.entry_format == entry.entry_format
+                && other.coord.clump_index == entry.coord.clump_index
+                && other.coord.coord_index == entry.coord.coord_index
+        });
+        if is_duplicate {
+            return Err(invalid(format!(
+                "the coord {}:{} has two entries with format {}",
+                entry.coord.clump_index, entry.coord.coord_index, entry.entry_format
+            )));
+        }
+
+        let curve_kinds = entry_curve_kinds(entry.entry_format).ok_or_else(|| {
+            invalid(format!(
+                "the entry of the coord {}:{} has the unknown format {}",
+                entry.coord.clump_index, entry.coord.coord_index, entry.entry_format
+            ))
+        })?;
+
+        if entry.curves.len() != curve_kinds.len() {
+            return Err(invalid(format!(
+                "the format {} entry of the coord {}:{} needs {} curves, not {}",
+                entry.entry_format,
+                entry.coord.clump_index,
+                entry.coord.coord_index,
+                curve_kinds.len(),
+                entry.curves.len()
+            )));
+        }
+
+        for (curve_index, (curve, curve_kind)) in entry.curves.iter().zip(curve_kinds).enumerate() {
+            if !curve_kind.matches(&curve.keys) {
+                return Err(invalid(format!(
+                    "curve {} of the format {} entry of the coord {}:{} has to be {}, not {}",
+                    curve_index,
+                    entry.entry_format,
+                    entry.coord.clump_index,
+                    entry.coord.coord_index,
+                    curve_kind.description(),
+                    curve.keys.type_name()
+                )));
+            }
+        }
+
+        let last_key_tick = if entry.entry_format == AnmEntryFormat::MATERIAL as u16 {
+            last_tick.saturating_add(frame_ticks / 2)
+        } else {
+            last_tick
+        };
+
+        for (curve_index, curve) in entry.curves.iter().enumerate() {
+            let value_count = match &curve.keys {
+                CurveKeysDump::QuaternionShort(values) => Some(values.len()),
+                CurveKeysDump::Float(values) => Some(values.len()),
+                CurveKeysDump::Vector3(values) => Some(values.len()),
+                CurveKeysDump::Rgb(values) => Some(values.len()),
+                _ => None,
+            };
+
+            if let Some(value_count) = value_count {
+                // Colors are padded to a multiple of 4 values
+                let padded_values = frame_values.next_multiple_of(4);
+                let is_padded =
+                    matches!(curve.keys, CurveKeysDump::Rgb(_)) && value_count == padded_values;
+
+                if value_count != 1 && value_count != frame_values && !is_padded {
+                    return Err(invalid(format!(
+                        "curve {} of the coord {}:{} needs 1 or {} values, not {}",
+                        curve_index,
+                        entry.coord.clump_index,
+                        entry.coord.coord_index,
+                        frame_values,
+                        value_count
+                    )));
+                }
+            }
+
+            let frames = match &curve.keys {
+                CurveKeysDump::KeyframeVector3(keyframes) => {
+                    keyframes.iter().map(|keyframe| keyframe.frame).collect()
+                }
+                CurveKeysDump::KeyframeVector4(keyframes) => {
+                    keyframes.iter().map(|keyframe| keyframe.frame).collect()
+                }
+                CurveKeysDump::KeyframeFloat(keyframes) => {
+                    keyframes.iter().map(|keyframe| keyframe.frame).collect()
+                }
+                _ => Vec::new(),
+            };
+
+            let frames = match frames.split_last() {
+                Some((-1, frames)) => frames,
+                _ => &frames[..],
+            };
+
+            let in_order = frames.windows(2).all(|pair| pair[0] < pair[1]);
+            let in_range = frames
+                .iter()
+                .all(|&frame| (0..=last_key_tick).contains(&frame));
+
+            if !in_order || !in_range {
+                return Err(invalid(format!(
+                    "curve {} of the coord {}:{} needs increasing keyframe frames from 0 to {}",
+                    curve_index, entry.coord.clump_index, entry.coord.coord_index, last_key_tick
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// What a curve of an entry holds, which decides the curve types it can be stored as.
+#[derive(Debug, Clone, Copy)]
+enum CurveKind {
+    Vector3,
+    Rotation,
+    Float,
+    Color,
+}
+
+impl CurveKind {
+    fn matches(&self, keys: &CurveKeysDump) -> bool {
+        matches!(
+            (self, keys),
+            (CurveKind::Vector3, CurveKeysDump::KeyframeVector3(_))
+                | (CurveKind::Vector3, CurveKeysDump::Vector3(_))
+                | (CurveKind::Rotation, CurveKeysDump::QuaternionShort(_))
+                | (CurveKind::Rotation, CurveKeysDump::KeyframeVector4(_))
+                | (CurveKind::Float, CurveKeysDump::Float(_))
+                | (CurveKind::Float, CurveKeysDump::KeyframeFloat(_))
+                | (CurveKind::Color, CurveKeysDump::Rgb(_))
+        )
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            CurveKind::Vector3 => "keyframe_vector3 or vector3",
+            CurveKind::Rotation => "quaternion_short or keyframe_vector4",
+            CurveKind::Float => "float or keyframe_float",
+            CurveKind::Color => "rgb",
+        }
+    }
+}
+
+impl CurveKeysDump {
+    /// The name of the curve type, as written in the `type` field.
+    fn type_name(&self) -> &'static str {
+        match self {
+            CurveKeysDump::KeyframeVector3(_) => "keyframe_vector3",
+            CurveKeysDump::KeyframeVector4(_) => "keyframe_vector4",
+            CurveKeysDump::QuaternionShort(_) => "quaternion_short",
+            CurveKeysDump::Float(_) => "float",
+            CurveKeysDump::KeyframeFloat(_) => "keyframe_float",
+            CurveKeysDump::Rgb(_) => "rgb",
+            CurveKeysDump::Vector3(_) => "vector3",
+        }
+    }
+}
+
+/// The curves an entry of the given format has, in the order the converter writes them.
+fn entry_curve_kinds(entry_format: u16) -> Option<&'static [CurveKind]> {
+    use CurveKind::*;
+
+    let curve_kinds: &'static [CurveKind] = match entry_format {
+        format if format == AnmEntryFormat::BONE as u16 => &[Vector3, Rotation, Vector3, Float],
+        format if format == AnmEntryFormat::CAMERA as u16 => &[Vector3, Rotation, Float],
+        format if format == AnmEntryFormat::MATERIAL as u16 => &[Float; 18],
+        format if format == AnmEntryFormat::LIGHTDIRC as u16 => &[Color, Float, Rotation],
+        format if format == AnmEntryFormat::LIGHTPOINT as u16 => {
+            &[Color, Float, Vector3, Float, Float]
+        }
+        format if format == AnmEntryFormat::AMBIENT as u16 => &[Color, Float],
+        format if format == AnmEntryFormat::MORPHMODEL as u16 => &[Float, Float],
+        _ => return None,
+    };
+
+    Some(curve_kinds)
+}
+
+/// The curve of some keys, without the null keyframe of a dumped keyed curve.
+fn import_curve_keys(keys: &CurveKeysDump) -> Curve {
+    match keys {
+        CurveKeysDump::KeyframeVector3(keyframes) => Curve::KeyframeVector3(
+            without_null_keyframe(keyframes, |keyframe| keyframe.frame)
+                .iter()
+                .map(|keyframe| KeyframeVector3 {
+                    frame: keyframe.frame,
+                    value: import_vector3(&keyframe.value),
+                })
+                .collect(),
+        ),
+        CurveKeysDump::KeyframeVector4(keyframes) => Curve::KeyframeVector4(
+            without_null_keyframe(keyframes, |keyframe| keyframe.frame)
+                .iter()
+                .map(
+                    |&KeyframeVector4Dump {
+                         frame,
+                         value: [x, y, z, w],
+                     }| KeyframeVector4 {
+                        frame,
+                        value: Vector4 { x, y, z, w },
+                    },
+                )
+                .collect(),
+        ),
+        CurveKeysDump::QuaternionShort(values) => Curve::QuaternionShort(
+            values
+                .iter()
+                .map(|&[x, y, z, w]| QuaternionShort { x, y, z, w })
+                .collect(),
+        ),
+        CurveKeysDump::Float(values) => Curve::Float(values.clone()),
+        CurveKeysDump::KeyframeFloat(keyframes) => Curve::KeyframeFloat(
+            without_null_keyframe(keyframes, |keyframe| keyframe.frame)
+                .iter()
+                .map(|keyframe| KeyframeFloat {
+                    frame: keyframe.frame,
+                    value: keyframe.value,
+                })
+                .collect(),
+        ),
+        CurveKeysDump::Rgb(values) => {
+            Curve::RGB(values.iter().map(|&[r, g, b]| RGB { r, g, b }).collect())
+        }
+        CurveKeysDump::Vector3(values) => {
+            Curve::Vector3(values.iter().map(import_vector3).collect())
+        }
+    }
+}
+
+fn without_null_keyframe<T>(keyframes: &[T], frame: impl Fn(&T) -> i32) -> &[T] {
+    match keyframes.split_last() {
+        Some((last, keyframes)) if frame(last) == -1 => keyframes,
+        _ => keyframes,
+    }
+}
+
+fn anm_coord(coord: &CoordDump) -> AnmCoord {
+    AnmCoord {
+        clump_index: coord.clump_index,
+        coord_index: coord.coord_index,
+    }
+}
+
+fn import_vector3(value: &[f32; 3]) -> Vector3 {
+    Vector3 {
+        x: value[0],
+        y: value[1],
+        z: value[2],
+    }
+}
+
+fn default_anm_version() -> u16 {
+    121
+}
+
+fn dump_clump(
+    clump_index: u32,
+    bone_material_indices: &[u32],
+    model_indices: &[u32],
+    struct_references: &[NuccStructReference],
+) -> ClumpDump {
+    ClumpDump {
+        clump: dump_reference(clump_index, struct_references),
+        bone_materials: dump_references(bone_material_indices, struct_references),
+        models: dump_references(model_indices, struct_references),
+    }
+}
+
+fn dump_references(
+    indices: &[u32],
+    struct_references: &[NuccStructReference],
+) -> Vec<ReferenceDump> {
+    indices
+        .iter()
+        .map(|&index| dump_reference(index, struct_references))
+        .collect()
+}
+
+/// Resolves a struct reference index, references outside the page get an empty chunk.
+fn dump_reference(index: u32, struct_references: &[NuccStructReference]) -> ReferenceDump {
+    match struct_references.get(index as usize) {
+        Some(reference) => ReferenceDump {
+            name: reference.chunk_name.clone(),
+            chunk_name: reference.struct_info.chunk_name.clone(),
+            chunk_type: reference.struct_info.chunk_type.clone(),
+            filepath: reference.struct_info.filepath.clone(),
+        },
+        None => ReferenceDump {
+            name: format!("<missing reference {}>", index),
+            chunk_name: String::new(),
+            chunk_type: String::new(),
+            filepath: String::new(),
+        },
+    }
+}
+
+fn dump_coord(coord: &AnmCoord) -> CoordDump {
+    CoordDump {
+        clump_index: coord.clump_index,
+        coord_index: coord.coord_index,
+    }
+}
+
+fn dump_coord_parent(coord_parent: &CoordParent) -> CoordParentDump {
+    CoordParentDump {
+        parent: dump_coord(&coord_parent.parent),
+        child: dump_coord(&coord_parent.child),
+    }
+}
+
+fn dump_strm_entry(entry: &AnmStrmEntry) -> StrmEntryDump {
+    let data = match &entry.entry_data {
+        Entry::Bone(bone) => StrmEntryDataDump::Bone {
+            location: vector3(&bone.location),
+            rotation: vector4(&bone.rotation),
+            scale: vector3(&bone.scale),
+            toggled: bone.toggled,
+        },
+        Entry::Material(material) => StrmEntryDataDump::Material {
+            ambient_color: material.ambient_color.clone(),
+        },
+        Entry::Camera(camera) => StrmEntryDataDump::Camera {
+            location: vector3(&camera.location),
+            rotation: vector4(&camera.rotation),
+            fov: camera.fov,
+        },
+        Entry::LightDirc(light) => StrmEntryDataDump::LightDirc {
+            color: vector3(&light.color),
+            intensity: light.intensity,
+            direction: vector4(&light.direction),
+        },
+        Entry::LightPoint(light) => StrmEntryDataDump::LightPoint {
+            color: vector3(&light.color),
+            intensity: light.intensity,
+            position: vector3(&light.position),
+            radius: light.radius,
+            falloff: light.falloff,
+        },
+        Entry::Ambient(ambient) => StrmEntryDataDump::Ambient {
+            color: vector3(&ambient.color),
+            intensity: ambient.intensity,
+        },
+        Entry::MorphModel(morph) => StrmEntryDataDump::MorphModel {
+            frame_count: morph.frame_count,
+            morph_weight: morph.morph_weight.clone(),
+        },
+        Entry::Unknown(bytes) => StrmEntryDataDump::Unknown {
+            bytes: bytes.clone(),
+        },
+    };
+
+    StrmEntryDump {
+        coord: dump_coord(&entry.coord),
+        entry_format: entry.entry_format,
+        data,
+    }
+}
+
+fn dump_anm_entry(entry: &AnmEntry) -> AnmEntryDump {
+    AnmEntryDump {
+        coord: dump_coord(&entry.coord),
+        entry_format: entry.entry_format,
+        curves: entry
+            .curves
+            .iter()
+            .enumerate()
+            .map(|(i, curve)| CurveDump {
+                header: entry.curve_headers.get(i).map(dump_curve_header).unwrap_or(
+                    CurveHeaderDump {
+                        curve_index: i as u16,
+                        curve_format: 0,
+                        frame_count: 0,
+                        curve_size: 0,
+                    },
+                ),
+                keys: dump_curve_keys(curve),
+            })
+            .collect(),
+    }
+}
+
+fn dump_curve_header(curve_header: &CurveHeader) -> CurveHeaderDump {
+    CurveHeaderDump {
+        curve_index: curve_header.curve_index,
+        curve_format: curve_header.curve_format,
+        frame_count: curve_header.frame_count,
+        curve_size: curve_header.curve_size,
+    }
+}
+
+fn dump_curve_keys(curve: &Curve) -> CurveKeysDump {
+    match curve {
+        Curve::KeyframeVector3(keyframes) => CurveKeysDump::KeyframeVector3(
+            keyframes
+                .iter()
+                .map(|keyframe| KeyframeVector3Dump {
+                    frame: keyframe.frame,
+                    value: vector3(&keyframe.value),
+                })
+                .collect(),
+        ),
+        Curve::KeyframeVector4(keyframes) => CurveKeysDump::KeyframeVector4(
+            keyframes
+                .iter()
+                .map(|keyframe| KeyframeVector4Dump {
+                    frame: keyframe.frame,
+                    value: vector4(&keyframe.value),
+                })
+                .collect(),
+        ),
+        Curve::QuaternionShort(values) => CurveKeysDump::QuaternionShort(
+            values
+                .iter()
+                .map(|value| [value.x, value.y, value.z, value.w])
+                .collect(),
+        ),
+        Curve::Float(values) => CurveKeysDump::Float(values.clone()),
+        Curve::KeyframeFloat(keyframes) => CurveKeysDump::KeyframeFloat(
+            keyframes
+                .iter()
+                .map(|keyframe| KeyframeFloatDump {
+                    frame: keyframe.frame,
+                    value: keyframe.value,
+                })
+                .collect(),
+        ),
+        Curve::RGB(values) => CurveKeysDump::Rgb(
+            values
+                .iter()
+                .map(|value| [value.r, value.g, value.b])
+                .collect(),
+        ),
+        Curve::Vector3(values) => CurveKeysDump::Vector3(values.iter().map(vector3).collect()),
+    }
+}
+
+fn vector3(value: &Vector3) -> [f32; 3] {
+    [value.x, value.y, value.z]
+}
+
+fn vector4(value: &Vector4) -> [f32; 4] {
+    [value.x, value.y, value.z, value.w]
+}
@@ -0,0 +1,419 @@
+//! .anmstrm converter for the Ultimate Ninja STORM series
+//!
+//! Converts the streamed cutscene animations (ANMSTRM) found in strm .xfbin files
+//! into regular ANM chunks. [`convert_strm_xfbin`] builds the whole converted xfbin
+//! in memory, so it can be written out with [`xfbin::write_xfbin`] or processed further.
+
+pub mod batch;
+pub mod bvh;
+pub mod converter;
+pub mod csv;
+pub mod error;
+pub mod gltf;
+pub mod json;
+pub mod looping;
+pub mod math;
+pub mod merge;
+pub mod optimize;
+pub mod remap;
+pub mod resample;
+pub mod reverse;
+pub mod sample;
+pub mod split;
+pub mod trim;
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use xfbin::nucc::nucc_anm::NuccAnm;
+use xfbin::nucc::{
+    NuccAmbient, NuccCamera, NuccLayerSet, NuccLightDirc, NuccLightPoint, NuccMorphModel,
+    NuccStruct, NuccStructInfo, NuccStructReference,
+};
+use xfbin::nucc_chunk::NuccChunkType;
+use xfbin::{xfbin::XfbinPage, Xfbin};
+
+use converter::{convert_anmstrm, ConversionReport, SkippedEntry};
+use looping::{LoopMode, LoopPop};
+use optimize::{KeyframeReduction, ReductionReport};
+use remap::{retain_referenced_struct_infos, retain_used_references};
+use sample::FRAME_TICKS;
+use trim::{trim_frame_settings_xml, FrameRange};
+use xml2fcv::{create_fcv_xfbin, get_frame_settings};
+
+pub use error::StrmConvError;
+
+/// The error returned by [`convert_strm_xfbin`].
+pub type ConvertError = StrmConvError;
+
+const CHUNK_TYPES_TO_ADD: [NuccChunkType; 6] = [
+    NuccChunkType::NuccChunkCamera,
+    NuccChunkType::NuccChunkLightDirc,
+    NuccChunkType::NuccChunkLightPoint,
+    NuccChunkType::NuccChunkMorphModel,
+    NuccChunkType::NuccChunkLayerSet,
+    NuccChunkType::NuccChunkAmbient,
+];
+
+/// Options for [`convert_strm_xfbin`].
+#[derive(Debug, Clone)]
+pub struct ConvertOptions {
+    /// Chunk name of the ANMSTRM to convert, the first ANMSTRM in the xfbin when `None`.
+    pub chunk_name: Option<String>,
+    /// Contents of the frame settings .xml, the FCV page is skipped when `None`.
+    pub frame_settings_xml: Option<String>,
+    /// How the clumps of the DMG anm page are chosen.
+    pub dmg_split: DmgSplit,
+    /// Hides progress bars and status messages.
+    pub quiet: bool,
+    /// Removes redundant bone keyframes within these tolerances when set.
+    pub keyframe_reduction: Option<KeyframeReduction>,
+    /// Writes curves that hold the same value on every frame with a single key.
+    pub collapse_constant_curves: bool,
+    /// Number of keyframe ticks in one frame, 100 for the Ultimate Ninja STORM games.
+    pub frame_ticks: u32,
+    /// Stretches the timing of the animation, 2 plays it at half speed or converts 30 to 60 fps.
+    pub time_scale: f32,
+    /// Only converts these frames and trims the frame settings .xml to them.
+    pub frame_range: Option<FrameRange>,
+    /// Whether the ANM loops, looped curves have their last frame closed to the first.
+    pub loop_mode: LoopMode,
+}
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        Self {
+            chunk_name: None,
+            frame_settings_xml: None,
+            dmg_split: DmgSplit::default(),
+            quiet: false,
+            keyframe_reduction: None,
+            collapse_constant_curves: false,
+            frame_ticks: FRAME_TICKS as u32,
+            time_scale: 1.0,
+            frame_range: None,
+            loop_mode: LoopMode::default(),
+        }
+    }
+}
+
+/// How the clumps of the DMG anm page are chosen.
+///
+/// The entries of the DMG clumps are moved from the ANM page to the DMG page.
+#[derive(Debug, Clone, Default)]
+pub enum DmgSplit {
+    /// The clump with 97 bones is the DMG clump and clumps with more bones are left out of the
+    /// DMG page, which matches the player characters of Ultimate Ninja STORM cutscenes. No DMG
+    /// page is built when no clump has 97 bones.
+    #[default]
+    Heuristic,
+    /// The clumps with one of these chunk names, every other clump is left out of the DMG page.
+    ChunkNames(Vec<String>),
+    /// The clumps whose filepath contains the pattern, every other clump is left out of the DMG page.
+    FilepathPattern(String),
+    /// The clumps at these indices of the ANM, every other clump is left out of the DMG page.
+    Indices(Vec<usize>),
+    /// Don't build the DMG page.
+    Disabled,
+}
+
+/// The result of [`convert_strm_xfbin`].
+pub struct ConvertOutput {
+    /// Chunk name of the converted ANM, used for the FCV page and the output file name.
+    pub chunk_name: String,
+    /// The converted xfbin with the ANM page, then the DMG and FCV pages if they were built.
+    pub xfbin: Xfbin,
+    /// The converted ANM.
+    pub anm: NuccAnm,
+    /// The DMG ANM, if it was built.
+    pub dmg_anm: Option<NuccAnm>,
+    /// Whether the FCV page was built.
+    pub has_fcv: bool,
+    /// How much was saved, if keyframe reduction or constant curve collapsing was enabled.
+    pub reduction_report: Option<ReductionReport>,
+    /// Entries of a type that can't be converted yet and were left out of the ANM.
+    pub skipped_entries: Vec<SkippedEntry>,
+    /// Curves of a looped ANM that visibly jump from their last to their first frame.
+    pub loop_pops: Vec<LoopPop>,
+}
+
+/// Converts the ANMSTRM of a strm xfbin into an xfbin with ANM, DMG and FCV pages.
+pub fn convert_strm_xfbin(
+    input: &Xfbin,
+    options: &ConvertOptions,
+) -> Result<ConvertOutput, ConvertError> {
+    let structs_to_add = collect_structs_to_add(input);
+
+    let anm_chunk_name = match &options.chunk_name {
+        Some(chunk_name) => chunk_name.clone(),
+        None => first_anmstrm_name(input).ok_or(StrmConvError::NoAnmStrmChunk)?,
+    };
+    let anm_chunk_name = anm_chunk_name.as_str();
+
+    let (page_index, anmstrm_info, mut anm_struct_references) =
+        get_page_info(input, anm_chunk_name).ok_or_else(|| StrmConvError::NoStructInfo {
+            chunk_name: anm_chunk_name.to_string(),
+        })?;
+
+    let mut anm_struct_infos = input.pages[page_index].struct_infos.clone();
+
+    let mut dmg_struct_infos = anm_struct_infos.clone();
+    let mut dmg_references = anm_struct_references.clone();
+
+    let dmg_anm_info = NuccStructInfo {
+        chunk_name: anm_chunk_name.to_string() + "_dmg",
+        chunk_type: NuccChunkType::NuccChunkAnm.to_string(),
+        filepath: anmstrm_info
+            .filepath
+            .clone()
+            .replace(anm_chunk_name, &(anm_chunk_name.to_string() + "_dmg")),
+    };
+
+    let mut new_xfbin = Xfbin::default();
+    let mut report = ConversionReport::default();
+
+    let mut converted_structs = convert_anmstrm(
+        input,
+        &anmstrm_info,
+        &dmg_anm_info,
+        &mut dmg_references,
+        &mut dmg_struct_infos,
+        &mut report,
+        options,
+    )?
+    .into_iter();
+
+    let mut anm = converted_structs
+        .next()
+        .ok_or(StrmConvError::NoAnmStrmChunk)?;
+    let mut dmg_anm = converted_structs.next();
+
+    // Only keep the references the pages point at, so no unused chunks end up in the ANM
+    let page_struct_references = anm_struct_references.clone();
+    retain_used_references(&mut anm, &mut anm_struct_references);
+
+    retain_referenced_struct_infos(
+        &mut anm_struct_infos,
+        &page_struct_references,
+        &anm_struct_references,
+    );
+
+    if let Some(dmg_anm) = &mut dmg_anm {
+        let page_struct_references = dmg_references.clone();
+        retain_used_references(dmg_anm, &mut dmg_references);
+        retain_referenced_struct_infos(
+            &mut dmg_struct_infos,
+            &page_struct_references,
+            &dmg_references,
+        );
+    }
+
+    let mut anm_page = XfbinPage {
+        struct_infos: anm_struct_infos,
+        struct_references: anm_struct_references,
+        structs: structs_to_add,
+    };
+    anm_page.struct_infos.push(anmstrm_info);
+    anm_page
+        .structs
+        .push(Box::new(anm.clone()) as Box<dyn NuccStruct>);
+
+    new_xfbin.pages.push(anm_page);
+
+    if let Some(dmg_anm) = &dmg_anm {
+        let mut dmg_anm_page = XfbinPage {
+            struct_infos: dmg_struct_infos,
+            struct_references: dmg_references,
+            structs: vec![Box::new(dmg_anm.clone()) as Box<dyn NuccStruct>],
+        };
+        dmg_anm_page.struct_infos.push(dmg_anm_info);
+
+        new_xfbin.pages.push(dmg_anm_page);
+    }
+
+    let has_fcv = match &options.frame_settings_xml {
+        Some(frame_settings_xml) => {
+            validate_xml(frame_settings_xml)?;
+
+            let frame_settings = match &options.frame_range {
+                Some(range) => {
+                    get_frame_settings(&trim_frame_settings_xml(frame_settings_xml, range)?)
+                }
+                None => get_frame_settings(frame_settings_xml),
+            };
+
+            let mut fcv_xfbin = Xfbin::default();
+            create_fcv_xfbin(&mut fcv_xfbin, &frame_settings, anm_chunk_name);
+
+            for page in fcv_xfbin.pages {
+                new_xfbin.pages.push(page);
+            }
+
+            true
+        }
+        None => false,
+    };
+
+    Ok(ConvertOutput {
+        chunk_name: anm_chunk_name.to_string(),
+        xfbin: new_xfbin,
+        anm,
+        dmg_anm,
+        has_fcv,
+        reduction_report: (options.keyframe_reduction.is_some()
+            || options.collapse_constant_curves)
+            .then_some(report.reduction),
+        skipped_entries: report.skipped_entries,
+        loop_pops: report.loop_pops,
+    })
+}
+
+/// Converts every ANMSTRM of a strm xfbin into its own xfbin, each with only its own frames.
+///
+/// `options.chunk_name` is ignored and the frame settings .xml is used for every ANMSTRM.
+pub fn convert_all_strm_xfbin(
+    input: &Xfbin,
+    options: &ConvertOptions,
+) -> Result<Vec<ConvertOutput>, ConvertError> {
+    let chunk_names = anmstrm_names(input);
+    if chunk_names.is_empty() {
+        return Err(StrmConvError::NoAnmStrmChunk);
+    }
+
+    chunk_names
+        .into_iter()
+        .map(|chunk_name| {
+            let options = ConvertOptions {
+                chunk_name: Some(chunk_name),
+                ..options.clone()
+            };
+
+            convert_strm_xfbin(input, &options)
+        })
+        .collect()
+}
+
+/// Reads through the whole .xml so malformed files are reported instead of panicking in xml2fcv.
+fn validate_xml(xml: &str) -> Result<(), StrmConvError> {
+    let mut reader = Reader::from_str(xml);
+
+    loop {
+        if let Event::Eof = reader.read_event()? {
+            return Ok(());
+        }
+    }
+}
+
+/// Collects the cameras, lights, morph models and layer sets that are copied over to the ANM page.
+fn collect_structs_to_add(xfbin: &Xfbin) -> Vec<Box<dyn NuccStruct>> {
+    let mut structs_to_add: Vec<Box<dyn NuccStruct>> = vec![];
+
+    for chunk_type in &CHUNK_TYPES_TO_ADD {
+        let nucc_structs = xfbin.find_nucc_structs(chunk_type.clone());
+
+        for nucc_struct in nucc_structs {
+            if let Some(nucc_struct) = clone_scene_struct(nucc_struct.as_ref()) {
+                structs_to_add.push(nucc_struct);
+            }
+        }
+    }
+
+    structs_to_add
+}
+
+/// Clones a camera, light, morph model or layer set, the chunks that live next to an animation.
+pub(crate) fn clone_scene_struct(nucc_struct: &dyn NuccStruct) -> Option<Box<dyn NuccStruct>> {
+    match nucc_struct.chunk_type() {
+        NuccChunkType::NuccChunkCamera => nucc_struct
+            .downcast_ref::<NuccCamera>()
+            .map(|camera| Box::new(camera.clone()) as Box<dyn NuccStruct>),
+        NuccChunkType::NuccChunkLightDirc => nucc_struct
+            .downcast_ref::<NuccLightDirc>()
+            .map(|lightdirc| Box::new(lightdirc.clone()) as Box<dyn NuccStruct>),
+        NuccChunkType::NuccChunkLightPoint => nucc_struct
+            .downcast_ref::<NuccLightPoint>()
+            .map(|lightpoint| Box::new(lightpoint.clone()) as Box<dyn NuccStruct>),
+        NuccChunkType::NuccChunkMorphModel => nucc_struct
+            .downcast_ref::<NuccMorphModel>()
+            .map(|morphmodel| Box::new(morphmodel.clone()) as Box<dyn NuccStruct>),
+        NuccChunkType::NuccChunkLayerSet => nucc_struct
+            .downcast_ref::<NuccLayerSet>()
+            .map(|layerset| Box::new(layerset.clone()) as Box<dyn NuccStruct>),
+        NuccChunkType::NuccChunkAmbient => nucc_struct
+            .downcast_ref::<NuccAmbient>()
+            .map(|ambient| Box::new(ambient.clone()) as Box<dyn NuccStruct>),
+        _ => None,
+    }
+}
+
+/// Returns the struct info of a camera, light, morph model or layer set.
+pub(crate) fn scene_struct_info(nucc_struct: &dyn NuccStruct) -> Option<&NuccStructInfo> {
+    match nucc_struct.chunk_type() {
+        NuccChunkType::NuccChunkCamera => nucc_struct
+            .downcast_ref::<NuccCamera>()
+            .map(|camera| &camera.struct_info),
+        NuccChunkType::NuccChunkLightDirc => nucc_struct
+            .downcast_ref::<NuccLightDirc>()
+            .map(|lightdirc| &lightdirc.struct_info),
+        NuccChunkType::NuccChunkLightPoint => nucc_struct
+            .downcast_ref::<NuccLightPoint>()
+            .map(|lightpoint| &lightpoint.struct_info),
+        NuccChunkType::NuccChunkMorphModel => nucc_struct
+            .downcast_ref::<NuccMorphModel>()
+            .map(|morphmodel| &morphmodel.struct_info),
+        NuccChunkType::NuccChunkLayerSet => nucc_struct
+            .downcast_ref::<NuccLayerSet>()
+            .map(|layerset| &layerset.struct_info),
+        NuccChunkType::NuccChunkAmbient => nucc_struct
+            .downcast_ref::<NuccAmbient>()
+            .map(|ambient| &ambient.struct_info),
+        _ => None,
+    }
+}
+
+/// Returns the chunk name of the first ANMSTRM in the xfbin.
+fn first_anmstrm_name(xfbin: &Xfbin) -> Option<String> {
+    anmstrm_names(xfbin).into_iter().next()
+}
+
+/// Returns the chunk names of every ANMSTRM in the xfbin, in the order they're stored.
+pub fn anmstrm_names(xfbin: &Xfbin) -> Vec<String> {
+    let mut chunk_names: Vec<String> = Vec::new();
+
+    for nucc_struct_info in xfbin.pages.iter().flat_map(|page| page.struct_infos.iter()) {
+        if nucc_struct_info.chunk_type == NuccChunkType::NuccChunkAnmStrm.to_string()
+            && !chunk_names.contains(&nucc_struct_info.chunk_name)
+        {
+            chunk_names.push(nucc_struct_info.chunk_name.clone());
+        }
+    }
+
+    chunk_names
+}
+
+fn get_page_info<'a>(
+    xfbin: &'a Xfbin,
+    chunk_name: &'a str,
+) -> Option<(usize, NuccStructInfo, Vec<NuccStructReference>)> {
+    let (page_index, mut anmstrm_info) =
+        xfbin
+            .pages
+            .iter()
+            .enumerate()
+            .find_map(|(page_index, page)| {
+                page.struct_infos
+                    .iter()
+                    .find(|nucc_struct_info| {
+                        nucc_struct_info.chunk_name == chunk_name
+                            && nucc_struct_info.chunk_type
+                                == NuccChunkType::NuccChunkAnmStrm.to_string()
+                    })
+                    .map(|nucc_struct_info| (page_index, nucc_struct_info.clone()))
+            })?;
+
+    anmstrm_info.chunk_type = NuccChunkType::NuccChunkAnm.to_string();
+
+    // The clumps and other entries of the ANMSTRM index into the references of its own page
+    let anm_struct_references = xfbin.pages[page_index].struct_references.clone();
+
+    Some((page_index, anmstrm_info, anm_struct_references))
+}
@@ -0,0 +1,211 @@
+use xfbin::nucc_chunk::nucc_chunk_anm::{AnmCoord, AnmEntry, Curve};
+use xfbin::nucc_chunk::nucc_helper::QuaternionShort;
+
+use crate::math::{quaternion_angle, quaternion_dot, vector3_distance};
+use crate::sample::decompress_quaternion;
+
+/// Largest location or scale jump between the last and first frame that is closed silently.
+const LOOP_DISTANCE_TOLERANCE: f32 = 0.01;
+/// Largest rotation jump in degrees between the last and first frame that is closed silently.
+const LOOP_ANGLE_TOLERANCE: f32 = 0.5;
+/// Largest float or color jump between the last and first frame that is closed silently.
+const LOOP_VALUE_TOLERANCE: f32 = 0.01;
+
+/// Whether the converted ANM loops.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LoopMode {
+    /// Loop if the ANMSTRM loops.
+    #[default]
+    Keep,
+    /// Always loop.
+    Force,
+    /// Never loop.
+    Clear,
+}
+
+impl LoopMode {
+    /// Whether an ANM converted from an ANMSTRM with the given looping loops.
+    pub fn is_looped(&self, anmstrm_is_looped: bool) -> bool {
+        match self {
+            LoopMode::Keep => anmstrm_is_looped,
+            LoopMode::Force => true,
+            LoopMode::Clear => false,
+        }
+    }
+}
+
+/// A curve of a looped animation whose last frame visibly differs from its first frame.
+#[derive(Debug, Clone)]
+pub struct LoopPop {
+    pub coord: AnmCoord,
+    pub entry_format: u16,
+    pub curve_index: usize,
+    /// How far the last frame is from the first, a distance or an angle in degrees for rotations.
+    pub difference: f32,
+}
+
+/// Makes the last frame of every curve wrap seamlessly to the first frame.
+///
+/// Every value of the last frame is set to the first one, which for keyed material colors is
+/// both the key on the frame and the key half a frame later. Keyed curves that stop before the
+/// last frame of the entry get a key there, and closed rotations stay on the side of the
+/// previous frame so they don't spin the long way into the loop point. Differences larger than
+/// rounding in the ANMSTRM change how the animation looks, so they are added to `pops`. This
+/// has to run before the null keyframes are appended and the curve headers are sized, so the
+/// null keyframe copies the closed value.
+pub fn close_loop(anm_entry: &mut AnmEntry, frame_ticks: i32, pops: &mut Vec<LoopPop>) {
+    let frame_ticks = frame_ticks.max(1);
+    let last_tick = last_frame(&anm_entry.curves, frame_ticks) * frame_ticks;
+
+    for (curve_index, curve) in anm_entry.curves.iter_mut().enumerate() {
+        let difference = match curve {
+            Curve::KeyframeVector3(keyframes) => {
+                if let Some(last) = keyframes.last().filter(|key| key.frame < last_tick) {
+                    let mut key = last.clone();
+                    key.frame = last_tick;
+                    keyframes.push(key);
+                }
+                let last_len = last_frame_len(keyframes.iter().map(|key| key.frame), frame_ticks);
+
+                close_values(
+                    keyframes,
+                    last_len,
+                    |a, b| vector3_distance(&a.value, &b.value),
+                    |last, first| last.value = first.value.clone(),
+                    LOOP_DISTANCE_TOLERANCE,
+                )
+            }
+            Curve::KeyframeFloat(keyframes) => {
+                if let Some(last) = keyframes.last().filter(|key| key.frame < last_tick) {
+                    let mut key = last.clone();
+                    key.frame = last_tick;
+                    keyframes.push(key);
+                }
+                let last_len = last_frame_len(keyframes.iter().map(|key| key.frame), frame_ticks);
+
+                close_values(
+                    keyframes,
+                    last_len,
+                    |a, b| (a.value - b.value).abs(),
+                    |last, first| last.value = first.value,
+                    LOOP_VALUE_TOLERANCE,
+                )
+            }
+            Curve::Float(values) => close_values(
+                values,
+                1,
+                |a, b| (a - b).abs(),
+                |last, first| *last = *first,
+                LOOP_VALUE_TOLERANCE,
+            ),
+            Curve::QuaternionShort(values) => {
+                let difference = close_values(
+                    values,
+                    1,
+                    |a, b| quaternion_angle(&decompress_quaternion(a), &decompress_quaternion(b)),
+                    |last, first| *last = first.clone(),
+                    LOOP_ANGLE_TOLERANCE,
+                );
+
+                if let [.., previous, last] = &mut values[..] {
+                    let previous = decompress_quaternion(previous);
+                    if quaternion_dot(&previous, &decompress_quaternion(last)) < 0.0 {
+                        *last = negate_quaternion(last);
+                    }
+                }
+
+                difference
+            }
+            Curve::RGB(values) => close_values(
+                values,
+                1,
+                |a, b| {
+                    let channel = |a: u8, b: u8| (a as f32 - b as f32).abs() / 255.0;
+
+                    channel(a.r, b.r)
+                        .max(channel(a.g, b.g))
+                        .max(channel(a.b, b.b))
+                },
+                |last, first| *last = first.clone(),
+                LOOP_VALUE_TOLERANCE,
+            ),
+            _ => None,
+        };
+
+        if let Some(difference) = difference {
+            pops.push(LoopPop {
+                coord: anm_entry.coord.clone(),
+                entry_format: anm_entry.entry_format,
+                curve_index,
+                difference,
+            });
+        }
+    }
+}
+
+/// Sets the last `last_len` values to the first one, and returns how far the furthest one was
+/// when that's more than `tolerance`.
+fn close_values<T>(
+    values: &mut [T],
+    last_len: usize,
+    difference: impl Fn(&T, &T) -> f32,
+    set_to_first: impl Fn(&mut T, &T),
+    tolerance: f32,
+) -> Option<f32> {
+    let (first, rest) = values.split_first_mut()?;
+    if last_len == 0 || rest.len() < last_len {
+        return None;
+    }
+
+    let last_start = rest.len() - last_len;
+    let last_frame = &mut rest[last_start..];
+
+    let difference = last_frame
+        .iter()
+        .map(|last| difference(last, first))
+        .fold(0.0, f32::max);
+    for last in last_frame {
+        set_to_first(last, first);
+    }
+
+    (difference > tolerance).then_some(difference)
+}
+
+/// The same rotation on the other side of the quaternion sphere.
+fn negate_quaternion(quaternion: &QuaternionShort) -> QuaternionShort {
+    QuaternionShort {
+        x: quaternion.x.saturating_neg(),
+        y: quaternion.y.saturating_neg(),
+        z: quaternion.z.saturating_neg(),
+        w: quaternion.w.saturating_neg(),
+    }
+}
+
+/// The last frame any curve of an entry has a value or key on.
+fn last_frame(curves: &[Curve], frame_ticks: i32) -> i32 {
+    curves
+        .iter()
+        .map(|curve| match curve {
+            Curve::KeyframeVector3(keyframes) => keyframes.last().map_or(0, |key| key.frame),
+            Curve::KeyframeVector4(keyframes) => keyframes.last().map_or(0, |key| key.frame),
+            Curve::KeyframeFloat(keyframes) => keyframes.last().map_or(0, |key| key.frame),
+            Curve::QuaternionShort(values) => values.len().saturating_sub(1) as i32 * frame_ticks,
+            Curve::Float(values) => values.len().saturating_sub(1) as i32 * frame_ticks,
+            Curve::RGB(values) => values.len().saturating_sub(1) as i32 * frame_ticks,
+            Curve::Vector3(values) => values.len().saturating_sub(1) as i32 * frame_ticks,
+        })
+        .max()
+        .unwrap_or(0)
+        / frame_ticks
+}
+
+/// Number of keys at the end of a keyed curve that are on its last frame.
+fn last_frame_len(frames: impl DoubleEndedIterator<Item = i32>, frame_ticks: i32) -> usize {
+    let mut frames = frames.rev().map(|frame| frame / frame_ticks).peekable();
+    let last_frame = match frames.peek() {
+        Some(last_frame) => *last_frame,
+        None => return 0,
+    };
+
+    frames.take_while(|frame| *frame == last_frame).count()
+}
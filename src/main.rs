@@ -1,208 +1,651 @@
-mod converter;
+mod cli;
 
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
 
-use xfbin::nucc::{
-    NuccAmbient, NuccCamera, NuccLayerSet, NuccLightDirc, NuccLightPoint, NuccMorphModel,
-    NuccStruct, NuccStructInfo, NuccStructReference,
-};
-use xfbin::nucc_chunk::NuccChunkType;
 use xfbin::{read_xfbin, write_xfbin};
-use xfbin::{xfbin::XfbinPage, Xfbin};
 
-use converter::convert_anmstrm;
-use xml2fcv::{create_fcv_xfbin, get_frame_settings};
+use cli::Args;
+use strmconv::batch::{find_batch_jobs, run_batch, BatchJob, BatchResult};
+use strmconv::bvh::{export_bvh, BvhOptions};
+use strmconv::converter::SkippedEntry;
+use strmconv::csv::{export_bone_csv, CsvOptions};
+use strmconv::gltf::{export_gltf, GltfOptions};
+use strmconv::json::{dump_xfbin, import_anm_json};
+use strmconv::looping::LoopPop;
+use strmconv::merge::{merge_anm_xfbins, MergeOptions};
+use strmconv::reverse::{convert_anm_xfbin, ReverseOptions};
+use strmconv::split::{split_anm_xfbin, SplitOptions};
+use strmconv::{
+    convert_all_strm_xfbin, convert_strm_xfbin, ConvertOptions, ConvertOutput, StrmConvError,
+};
+
+fn main() -> ExitCode {
+    let args = Args::parse();
 
-const CHUNK_TYPES_TO_ADD: [NuccChunkType; 6] = [
-    NuccChunkType::NuccChunkCamera,
-    NuccChunkType::NuccChunkLightDirc,
-    NuccChunkType::NuccChunkLightPoint,
-    NuccChunkType::NuccChunkMorphModel,
-    NuccChunkType::NuccChunkLayerSet,
-    NuccChunkType::NuccChunkAmbient,
-];
+    match run_mode(&args) {
+        Ok(exit_code) => exit_code,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Runs the mode picked by the arguments, converting the strm .xfbin when no other mode is.
+fn run_mode(args: &Args) -> Result<ExitCode, StrmConvError> {
+    if let Some(batch_dir) = &args.batch {
+        return run_batch_mode(args, batch_dir);
+    }
+
+    if let Some(json_path) = &args.import {
+        run_import(args, json_path)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let (filepath, xml_path) = match args.input_files() {
+        Ok(files) => files,
+        Err(message) => Args::command()
+            .error(ErrorKind::ValueValidation, message)
+            .exit(),
+    };
 
-fn main() {
+    if args.dump {
+        run_dump(args, &filepath)?;
+    } else if args.bvh {
+        run_bvh(args, &filepath)?;
+    } else if args.csv {
+        run_csv(args, &filepath)?;
+    } else if !args.merge.is_empty() {
+        run_merge(args, &filepath)?;
+    } else if !args.split.is_empty() {
+        run_split(args, &filepath)?;
+    } else if args.to_strm {
+        run_to_strm(args, &filepath)?;
+    } else {
+        run(args, &filepath, xml_path.as_deref())?;
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run(args: &Args, filepath: &Path, xml_path: Option<&Path>) -> Result<(), StrmConvError> {
     let time = std::time::Instant::now();
 
-    let args: Vec<String> = std::env::args().collect();
+    let xfbin = read_xfbin(filepath).map_err(|err| StrmConvError::Xfbin(err.to_string()))?;
+    if !args.quiet {
+        println!("Converting file: {:?}", display_name(filepath));
+    }
+
+    let frame_settings_xml = match (xml_path, args.no_fcv) {
+        (Some(xml_path), false) => {
+            if !args.quiet {
+                println!("Parsing fcurve settings from '{}'", display_name(xml_path));
+            }
+
+            Some(fs::read_to_string(xml_path)?)
+        }
+        _ => None,
+    };
+
+    let options = ConvertOptions {
+        chunk_name: args.name.clone(),
+        frame_settings_xml,
+        dmg_split: args.dmg_split(),
+        quiet: args.quiet,
+        keyframe_reduction: args.keyframe_reduction(),
+        collapse_constant_curves: args.collapse_constant,
+        frame_ticks: args.frame_ticks,
+        time_scale: args.time_scale(),
+        frame_range: args.frames,
+        loop_mode: args.loop_mode(),
+    };
+
+    let outputs = if args.all_streams {
+        convert_all_strm_xfbin(&xfbin, &options)?
+    } else {
+        vec![convert_strm_xfbin(&xfbin, &options)?]
+    };
+
+    if let Some(output_dir) = &args.output_dir {
+        fs::create_dir_all(output_dir)?;
+    }
 
-    if args.len() != 3 {
-        panic!(
-            "Invalid number of arguments! Expected 3, got {}",
-            args.len() - 1
+    for output in outputs {
+        write_converted(args, output)?;
+    }
+
+    if !args.quiet {
+        println!(
+            "Finished converting strm to anm in {:?}s \n",
+            time.elapsed().as_secs_f64()
         );
     }
 
-    let filepath = Path::new(args.iter().find(|arg| arg.ends_with(".xfbin")).unwrap());
+    Ok(())
+}
 
-    let xfbin = read_xfbin(&filepath).unwrap();
-    println!(
-        "Converting file: {:?}",
-        filepath.file_name().unwrap().to_str().unwrap()
+/// Prints the report of a converted ANMSTRM and writes its xfbin.
+fn write_converted(args: &Args, output: ConvertOutput) -> Result<(), StrmConvError> {
+    if args.all_streams && !args.quiet {
+        println!("Converted ANMSTRM '{}'", output.chunk_name);
+    }
+
+    if let (Some(report), false) = (&output.reduction_report, args.quiet) {
+        if args.reduce {
+            println!(
+                "Bone curve reduction kept {} of {} bone keys and values ({:.1}% saved, {} static curves)",
+                report.keyframes_after,
+                report.keyframes_before,
+                report.saved_percent(),
+                report.static_curves
+            );
+        }
+
+        if args.collapse_constant {
+            println!(
+                "Collapsed {} constant curves to a single key",
+                report.constant_curves
+            );
+        }
+    }
+
+    if !output.skipped_entries.is_empty() && !args.quiet {
+        print_skipped_entries(&output.skipped_entries);
+    }
+
+    if !output.loop_pops.is_empty() && !args.quiet {
+        print_loop_pops(&output.loop_pops);
+    }
+
+    if !output.has_fcv && !args.quiet {
+        if args.no_fcv {
+            println!("Skipping the FCV page (--no-fcv)");
+        } else {
+            println!("No frame settings .xml was given, skipping the FCV page");
+        }
+    }
+
+    let converted_path = output_path(args, &output.chunk_name, ".anm.xfbin");
+
+    if args.gltf || args.glb {
+        write_gltf(args, &output, &converted_path)?;
+    }
+
+    write_xfbin(output.xfbin, &converted_path)
+        .map_err(|err| StrmConvError::Xfbin(err.to_string()))?;
+
+    if !args.quiet {
+        println!("Wrote '{}'", converted_path.display());
+    }
+
+    Ok(())
+}
+
+/// Exports the converted anm to glTF next to the anm .xfbin.
+fn write_gltf(
+    args: &Args,
+    output: &ConvertOutput,
+    converted_path: &Path,
+) -> Result<(), StrmConvError> {
+    let options = GltfOptions {
+        fps: args.fps.map_or(30.0, |(_, to)| to),
+        frame_ticks: args.frame_ticks as i32,
+    };
+
+    // The DMG ANM, when there is one, is on the page after the main ANM
+    let page_references = |page_index: usize| {
+        output
+            .xfbin
+            .pages
+            .get(page_index)
+            .map(|page| page.struct_references.as_slice())
+            .unwrap_or_default()
+    };
+
+    let mut anms = vec![(&output.anm, page_references(0))];
+    if let Some(dmg_anm) = &output.dmg_anm {
+        anms.push((dmg_anm, page_references(1)));
+    }
+
+    let export = export_gltf(&anms, &options);
+    let gltf_path = converted_path.with_extension(if args.glb { "glb" } else { "gltf" });
+    export.write(&gltf_path)?;
+
+    if !args.quiet {
+        if export.skipped_entries > 0 {
+            println!(
+                "Left {} material, ambient light and morph entries out of the glTF",
+                export.skipped_entries
+            );
+        }
+
+        println!("Wrote '{}'", gltf_path.display());
+    }
+
+    Ok(())
+}
+
+/// Warns about the entries that couldn't be converted.
+fn print_skipped_entries(skipped_entries: &[SkippedEntry]) {
+    let mut kinds = skipped_entries
+        .iter()
+        .map(|skipped_entry| skipped_entry.kind.as_str())
+        .collect::<Vec<_>>();
+    kinds.sort_unstable();
+    kinds.dedup();
+
+    eprintln!(
+        "warning: skipped {} entries that xfbin couldn't parse ({}):",
+        skipped_entries.len(),
+        kinds.join(", ")
+    );
+
+    for skipped_entry in skipped_entries {
+        eprintln!(
+            "  {} at clump {}, coord {}: {} frames dropped",
+            skipped_entry.kind,
+            skipped_entry.coord.clump_index,
+            skipped_entry.coord.coord_index,
+            skipped_entry.frames
+        );
+    }
+}
+
+/// Warns about the curves that visibly jump at the loop point.
+fn print_loop_pops(loop_pops: &[LoopPop]) {
+    eprintln!(
+        "warning: {} curves don't match their first frame at the loop point:",
+        loop_pops.len()
     );
 
-    let mut structs_to_add: Vec<Box<dyn NuccStruct>> = vec![];
-
-    for chunk_type in &CHUNK_TYPES_TO_ADD {
-        let nucc_structs = xfbin.find_nucc_structs(chunk_type.clone());
-
-        for nucc_struct in nucc_structs {
-            match chunk_type {
-                NuccChunkType::NuccChunkCamera => {
-                    if let Some(camera) = nucc_struct.downcast_ref::<NuccCamera>() {
-                        structs_to_add.push(Box::new(camera.clone()));
-                    }
-                }
-                NuccChunkType::NuccChunkLightDirc => {
-                    if let Some(lightdirc) = nucc_struct.downcast_ref::<NuccLightDirc>() {
-                        structs_to_add.push(Box::new(lightdirc.clone()));
-                    }
-                }
-                NuccChunkType::NuccChunkLightPoint => {
-                    if let Some(lightpoint) = nucc_struct.downcast_ref::<NuccLightPoint>() {
-                        structs_to_add.push(Box::new(lightpoint.clone()));
-                    }
-                }
-                NuccChunkType::NuccChunkMorphModel => {
-                    if let Some(morphmodel) = nucc_struct.downcast_ref::<NuccMorphModel>() {
-                        structs_to_add.push(Box::new(morphmodel.clone()));
-                    }
-                }
-                NuccChunkType::NuccChunkLayerSet => {
-                    if let Some(layerset) = nucc_struct.downcast_ref::<NuccLayerSet>() {
-                        structs_to_add.push(Box::new(layerset.clone()));
-                    }
-                }
-                NuccChunkType::NuccChunkAmbient => {
-                    if let Some(ambient) = nucc_struct.downcast_ref::<NuccAmbient>() {
-                        structs_to_add.push(Box::new(ambient.clone()));
-                    }
-                }
-
-                _ => {}
+    for loop_pop in loop_pops {
+        eprintln!(
+            "  clump {}, coord {}, curve {} (format {}): off by {:.3}",
+            loop_pop.coord.clump_index,
+            loop_pop.coord.coord_index,
+            loop_pop.curve_index,
+            loop_pop.entry_format,
+            loop_pop.difference
+        );
+    }
+}
+
+/// Converts an anm xfbin back into a strm xfbin.
+fn run_to_strm(args: &Args, filepath: &Path) -> Result<(), StrmConvError> {
+    let time = std::time::Instant::now();
+
+    let xfbin = read_xfbin(filepath).map_err(|err| StrmConvError::Xfbin(err.to_string()))?;
+    if !args.quiet {
+        println!("Converting file: {:?}", display_name(filepath));
+    }
+
+    let options = ReverseOptions {
+        chunk_name: args.name.clone(),
+        merge_dmg: !args.no_dmg,
+        frame_ticks: args.frame_ticks as i32,
+    };
+
+    let output = convert_anm_xfbin(&xfbin, &options)?;
+
+    if output.merged_dmg && !args.quiet {
+        println!("Merged the DMG anm back into '{}'", output.chunk_name);
+    }
+
+    let converted_path = output_path(args, &output.chunk_name, ".strm.xfbin");
+    if let Some(output_dir) = &args.output_dir {
+        fs::create_dir_all(output_dir)?;
+    }
+    write_xfbin(output.xfbin, &converted_path)
+        .map_err(|err| StrmConvError::Xfbin(err.to_string()))?;
+
+    if !args.quiet {
+        println!(
+            "Finished converting anm to strm file '{}' in {:?}s \n",
+            converted_path.display(),
+            time.elapsed().as_secs_f64()
+        );
+    }
+
+    Ok(())
+}
+
+/// Splits the clumps of an anm xfbin into one anm xfbin per group.
+fn run_split(args: &Args, filepath: &Path) -> Result<(), StrmConvError> {
+    let time = std::time::Instant::now();
+
+    let xfbin = read_xfbin(filepath).map_err(|err| StrmConvError::Xfbin(err.to_string()))?;
+    if !args.quiet {
+        println!("Splitting file: {:?}", display_name(filepath));
+    }
+
+    let options = SplitOptions {
+        chunk_name: args.name.clone(),
+        groups: args.split.clone(),
+        keep_other_entries: args.split_keep_other,
+    };
+
+    let outputs = split_anm_xfbin(&xfbin, &options)?;
+
+    if let Some(output_dir) = &args.output_dir {
+        fs::create_dir_all(output_dir)?;
+    }
+
+    for output in outputs {
+        let split_path = output_path(args, &output.chunk_name, ".anm.xfbin");
+        write_xfbin(output.xfbin, &split_path)
+            .map_err(|err| StrmConvError::Xfbin(err.to_string()))?;
+
+        if !args.quiet {
+            println!("Wrote '{}'", split_path.display());
+        }
+    }
+
+    if !args.quiet {
+        println!(
+            "Finished splitting anm file in {:?}s \n",
+            time.elapsed().as_secs_f64()
+        );
+    }
+
+    Ok(())
+}
+
+/// Dumps the ANMSTRMs and ANMs of an xfbin to JSON.
+fn run_dump(args: &Args, filepath: &Path) -> Result<(), StrmConvError> {
+    let xfbin = read_xfbin(filepath).map_err(|err| StrmConvError::Xfbin(err.to_string()))?;
+    let dump = dump_xfbin(&xfbin, !args.dump_headers_only)?;
+    let json = serde_json::to_string_pretty(&dump)?;
+
+    match &args.output {
+        Some(output) => {
+            fs::write(output, json)?;
+
+            if !args.quiet {
+                println!("Wrote '{}'", output.display());
             }
         }
+        None => println!("{}", json),
     }
 
-    let anm_chunk_name = filepath
-        .file_stem()
-        .unwrap()
-        .to_str()
-        .unwrap()
-        .split('.')
-        .next()
-        .unwrap();
+    Ok(())
+}
 
-    let (anmstrm_info, anm_struct_references) = get_page_info(&xfbin, anm_chunk_name);
+/// Writes the bone transforms of an ANMSTRM to CSV.
+fn run_csv(args: &Args, filepath: &Path) -> Result<(), StrmConvError> {
+    let xfbin = read_xfbin(filepath).map_err(|err| StrmConvError::Xfbin(err.to_string()))?;
 
-    let anm_struct_infos = xfbin.pages[0].struct_infos.clone();
+    let options = CsvOptions {
+        chunk_name: args.name.clone(),
+        frame_ticks: args.frame_ticks as i32,
+    };
+    let csv = export_bone_csv(&xfbin, &options)?;
 
-    let mut dmg_struct_infos = anm_struct_infos.clone();
-    let mut dmg_references = anm_struct_references.clone();
+    if let Some(output_dir) = &args.output_dir {
+        fs::create_dir_all(output_dir)?;
+    }
 
-    let dmg_anm_info = NuccStructInfo {
-        chunk_name: anm_chunk_name.to_string() + "_dmg",
-        chunk_type: NuccChunkType::NuccChunkAnm.to_string(),
-        filepath: anmstrm_info
-            .filepath
-            .clone()
-            .replace(anm_chunk_name, &(anm_chunk_name.to_string() + "_dmg")),
+    let chunk_name = match &args.name {
+        Some(name) => name.clone(),
+        None => strmconv::anmstrm_names(&xfbin)
+            .into_iter()
+            .next()
+            .unwrap_or_default(),
     };
+    let csv_path = output_path(args, &chunk_name, ".csv");
+    fs::write(&csv_path, csv)?;
 
-    let mut new_xfbin = Xfbin::default();
-
-    let converted_structs = convert_anmstrm(
-        &xfbin,
-        &anmstrm_info,
-        &dmg_anm_info,
-        &mut dmg_references,
-        &mut dmg_struct_infos,
-    )
-    .unwrap()
-    .iter()
-    .cloned()
-    .collect::<Vec<_>>();
-
-    let mut anm_page = XfbinPage::default();
-    anm_page.struct_infos = anm_struct_infos.clone();
-    anm_page.struct_infos.push(anmstrm_info.clone());
-    anm_page.struct_references = anm_struct_references.clone();
-    anm_page.structs.extend(structs_to_add);
-    anm_page
-        .structs
-        .push(Box::new(converted_structs[0].clone()) as Box<dyn NuccStruct>);
-
-    let mut dmg_anm_page = XfbinPage::default();
-    dmg_anm_page.struct_infos = dmg_struct_infos;
-    dmg_anm_page.struct_infos.push(dmg_anm_info.clone());
-    dmg_anm_page.struct_references = dmg_references;
-    dmg_anm_page.structs = vec![Box::new(converted_structs[1].clone()) as Box<dyn NuccStruct>];
-
-    new_xfbin.pages.push(anm_page);
-    new_xfbin.pages.push(dmg_anm_page);
-
-    let xml_path = args.iter().find(|arg| arg.ends_with(".xml")).unwrap();
-    let frame_settings = get_frame_settings(fs::read_to_string(xml_path).unwrap().as_str());
-    println!(
-        "Parsing fcurve settings from '{}'",
-        Path::new(xml_path).file_name().unwrap().to_str().unwrap()
-    );
+    if !args.quiet {
+        println!("Wrote '{}'", csv_path.display());
+    }
+
+    Ok(())
+}
+
+/// Writes the bones of every clump of an ANMSTRM to BVH.
+fn run_bvh(args: &Args, filepath: &Path) -> Result<(), StrmConvError> {
+    let xfbin = read_xfbin(filepath).map_err(|err| StrmConvError::Xfbin(err.to_string()))?;
 
-    let mut fcv_xfbin = Xfbin::default();
-    create_fcv_xfbin(&mut fcv_xfbin, &frame_settings, anm_chunk_name);
+    let chunk_name = match &args.name {
+        Some(name) => name.clone(),
+        None => strmconv::anmstrm_names(&xfbin)
+            .into_iter()
+            .next()
+            .ok_or(StrmConvError::NoAnmStrmChunk)?,
+    };
+
+    let options = BvhOptions {
+        chunk_name: Some(chunk_name.clone()),
+        frame_ticks: args.frame_ticks as i32,
+        fps: args.fps.map_or(30.0, |(_, to)| to),
+    };
+    let outputs = export_bvh(&xfbin, &options)?;
 
-    for page in fcv_xfbin.pages {
-        new_xfbin.pages.push(page);
+    if let Some(output_dir) = &args.output_dir {
+        fs::create_dir_all(output_dir)?;
     }
 
-    let converted_filename = anm_chunk_name.to_string() + ".anm.xfbin";
-    write_xfbin(new_xfbin, &Path::new(converted_filename.as_str())).unwrap();
+    for output in outputs {
+        let bvh_path = output_path(
+            args,
+            &format!("{}_{}", chunk_name, output.clump_name),
+            ".bvh",
+        );
+        fs::write(&bvh_path, output.bvh)?;
 
-    println!(
-        "Finished converting strm to anm file '{}' in {:?}s \n",
-        converted_filename,
-        time.elapsed().as_secs_f64()
-    );
+        if !args.quiet {
+            println!("Wrote '{}'", bvh_path.display());
+        }
+    }
+
+    Ok(())
 }
 
-fn get_page_info<'a>(
-    xfbin: &'a Xfbin,
-    chunk_name: &'a str,
-) -> (NuccStructInfo, Vec<NuccStructReference>) {
-    let anm_struct_references = xfbin
-        .pages
-        .iter()
-        .flat_map(|page| {
-            page.struct_references.iter().filter_map(|nucc_struct_ref| {
-                if let NuccChunkType::NuccChunkAnmStrm = NuccChunkType::NuccChunkAnmStrm {
-                    Some(nucc_struct_ref.clone())
-                } else {
-                    None
-                }
-            })
+/// Builds an anm xfbin from a JSON description.
+fn run_import(args: &Args, json_path: &Path) -> Result<(), StrmConvError> {
+    let json = fs::read_to_string(json_path)?;
+    let output = import_anm_json(&json, args.frame_ticks as i32)?;
+
+    if let Some(output_dir) = &args.output_dir {
+        fs::create_dir_all(output_dir)?;
+    }
+
+    let imported_path = output_path(args, &output.chunk_name, ".anm.xfbin");
+    write_xfbin(output.xfbin, &imported_path)
+        .map_err(|err| StrmConvError::Xfbin(err.to_string()))?;
+
+    if !args.quiet {
+        println!("Wrote '{}'", imported_path.display());
+    }
+
+    Ok(())
+}
+
+/// Merges the ANMs of several xfbins into one anm xfbin.
+fn run_merge(args: &Args, filepath: &Path) -> Result<(), StrmConvError> {
+    let time = std::time::Instant::now();
+
+    let inputs = std::iter::once(filepath)
+        .chain(args.merge.iter().map(PathBuf::as_path))
+        .map(|path| {
+            if !args.quiet {
+                println!("Merging file: {:?}", display_name(path));
+            }
+
+            read_xfbin(path).map_err(|err| StrmConvError::Xfbin(err.to_string()))
         })
-        .collect::<Vec<_>>();
+        .collect::<Result<Vec<_>, _>>()?;
 
-    let mut anmstrm_info: NuccStructInfo = xfbin
-        .pages
-        .iter()
-        .flat_map(|page| {
-            page.struct_infos.iter().filter_map(|nucc_struct_info| {
-                if nucc_struct_info.chunk_name == chunk_name
-                    && nucc_struct_info.chunk_type == NuccChunkType::NuccChunkAnmStrm.to_string()
-                {
-                    Some(nucc_struct_info.clone())
-                } else {
-                    None
-                }
+    let options = MergeOptions {
+        chunk_name: args.name.clone(),
+    };
+
+    let output = merge_anm_xfbins(&inputs, &options)?;
+
+    if !output.conflicts.is_empty() && !args.quiet {
+        eprintln!(
+            "warning: {} entries are animated by more than one anm, the first one was kept:",
+            output.conflicts.len()
+        );
+
+        for conflict in &output.conflicts {
+            eprintln!(
+                "  {} at clump {}, coord {} (format {}) from anm #{}",
+                conflict.chunk_name.as_deref().unwrap_or("<unknown>"),
+                conflict.coord.clump_index,
+                conflict.coord.coord_index,
+                conflict.entry_format,
+                conflict.source + 1
+            );
+        }
+    }
+
+    let merged_path = output_path(args, &output.chunk_name, ".anm.xfbin");
+    if let Some(output_dir) = &args.output_dir {
+        fs::create_dir_all(output_dir)?;
+    }
+    write_xfbin(output.xfbin, &merged_path).map_err(|err| StrmConvError::Xfbin(err.to_string()))?;
+
+    if !args.quiet {
+        println!(
+            "Finished merging into anm file '{}' in {:?}s \n",
+            merged_path.display(),
+            time.elapsed().as_secs_f64()
+        );
+    }
+
+    Ok(())
+}
+
+/// Converts a whole directory and prints a summary of every file.
+fn run_batch_mode(args: &Args, batch_dir: &Path) -> Result<ExitCode, StrmConvError> {
+    let time = std::time::Instant::now();
+
+    let jobs = find_batch_jobs(batch_dir, args.recursive)?;
+    if jobs.is_empty() {
+        eprintln!(
+            "no *.strm.xfbin files were found in '{}'",
+            batch_dir.display()
+        );
+        return Ok(ExitCode::FAILURE);
+    }
+
+    if let Some(output_dir) = &args.output_dir {
+        fs::create_dir_all(output_dir)?;
+    }
+
+    let options = ConvertOptions {
+        chunk_name: None,
+        frame_settings_xml: None,
+        dmg_split: args.dmg_split(),
+        quiet: args.quiet,
+        keyframe_reduction: args.keyframe_reduction(),
+        collapse_constant_curves: args.collapse_constant,
+        frame_ticks: args.frame_ticks,
+        time_scale: args.time_scale(),
+        frame_range: args.frames,
+        loop_mode: args.loop_mode(),
+    };
+
+    let jobs = if args.no_fcv {
+        jobs.into_iter()
+            .map(|job| BatchJob {
+                xml_path: None,
+                ..job
             })
-        })
-        .next()
-        .unwrap();
+            .collect()
+    } else {
+        jobs
+    };
+
+    let results = run_batch(&jobs, &options, args.output_dir.as_deref());
+    let failed = results
+        .iter()
+        .filter(|result| result.result.is_err())
+        .count();
+
+    if !args.quiet {
+        print_batch_summary(&results);
+        println!(
+            "{} converted, {} failed in {:?}s \n",
+            results.len() - failed,
+            failed,
+            time.elapsed().as_secs_f64()
+        );
+    } else {
+        for result in &results {
+            if let Err(err) = &result.result {
+                eprintln!("error: {}: {}", result.job.xfbin_path.display(), err);
+            }
+        }
+    }
+
+    Ok(if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+/// Prints a table with the status, time and output or error of every converted file.
+fn print_batch_summary(results: &[BatchResult]) {
+    let name_width = results
+        .iter()
+        .map(|result| result.job.chunk_name.len())
+        .max()
+        .unwrap_or(0)
+        .max("chunk".len());
+
+    println!();
+    println!(
+        "{:<6}  {:<name_width$}  {:>9}  {:<6}  output / error",
+        "status", "chunk", "time", "xml"
+    );
+
+    for result in results {
+        let (status, message) = match &result.result {
+            Ok(converted_path) => ("ok", converted_path.display().to_string()),
+            Err(err) => ("FAILED", err.to_string()),
+        };
+
+        println!(
+            "{:<6}  {:<name_width$}  {:>8.2}s  {:<6}  {}",
+            status,
+            result.job.chunk_name,
+            result.elapsed.as_secs_f64(),
+            if result.job.xml_path.is_some() {
+                "yes"
+            } else {
+                "no"
+            },
+            message
+        );
+    }
+
+    println!();
+}
+
+/// Returns where the converted xfbin should be written to.
+fn output_path(args: &Args, chunk_name: &str, extension: &str) -> PathBuf {
+    if let Some(output) = &args.output {
+        return output.clone();
+    }
 
-    anmstrm_info.chunk_type = NuccChunkType::NuccChunkAnm.to_string();
+    let converted_filename = chunk_name.to_string() + extension;
+
+    match &args.output_dir {
+        Some(output_dir) => output_dir.join(converted_filename),
+        None => PathBuf::from(converted_filename),
+    }
+}
 
-    (anmstrm_info, anm_struct_references)
+/// Returns the file name of a path for printing.
+fn display_name(path: &Path) -> String {
+    path.file_name()
+        .map(|file_name| file_name.to_string_lossy().into_owned())
+        .unwrap_or_default()
 }
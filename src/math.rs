@@ -0,0 +1,108 @@
+use xfbin::nucc_chunk::nucc_helper::*;
+
+/// Dot product of two quaternions.
+pub fn quaternion_dot(a: &Vector4, b: &Vector4) -> f32 {
+    a.x * b.x + a.y * b.y + a.z * b.z + a.w * b.w
+}
+
+/// Scales a quaternion to unit length, a zero quaternion becomes the identity rotation.
+pub fn normalize_quaternion(quaternion: &Vector4) -> Vector4 {
+    let length = quaternion_dot(quaternion, quaternion).sqrt();
+
+    if length == 0.0 || !length.is_finite() {
+        return Vector4 {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+            w: 1.0,
+        };
+    }
+
+    Vector4 {
+        x: quaternion.x / length,
+        y: quaternion.y / length,
+        z: quaternion.z / length,
+        w: quaternion.w / length,
+    }
+}
+
+/// Angle in degrees of the rotation between two quaternions.
+pub fn quaternion_angle(a: &Vector4, b: &Vector4) -> f32 {
+    let length = (quaternion_dot(a, a) * quaternion_dot(b, b)).sqrt();
+    if length == 0.0 {
+        return 0.0;
+    }
+
+    // q and -q are the same rotation, so the absolute dot is used
+    let cos_half_angle = (quaternion_dot(a, b).abs() / length).min(1.0);
+
+    (2.0 * cos_half_angle.acos()).to_degrees()
+}
+
+/// Distance between two vectors.
+pub fn vector3_distance(a: &Vector3, b: &Vector3) -> f32 {
+    ((a.x - b.x).powi(2) + (a.y - b.y).powi(2) + (a.z - b.z).powi(2)).sqrt()
+}
+
+/// Linear interpolation between two vectors.
+pub fn vector3_lerp(a: &Vector3, b: &Vector3, t: f32) -> Vector3 {
+    Vector3 {
+        x: a.x + (b.x - a.x) * t,
+        y: a.y + (b.y - a.y) * t,
+        z: a.z + (b.z - a.z) * t,
+    }
+}
+
+/// Spherical linear interpolation between two quaternions, taking the shorter way round.
+pub fn quaternion_slerp(a: &Vector4, b: &Vector4, t: f32) -> Vector4 {
+    let a = normalize_quaternion(a);
+    let mut b = normalize_quaternion(b);
+
+    let mut cos_angle = quaternion_dot(&a, &b);
+    if cos_angle < 0.0 {
+        cos_angle = -cos_angle;
+        b = Vector4 {
+            x: -b.x,
+            y: -b.y,
+            z: -b.z,
+            w: -b.w,
+        };
+    }
+
+    // Nearly equal rotations are lerped, the slerp weights would divide by almost zero
+    let (weight_a, weight_b) = if cos_angle > 0.9995 {
+        (1.0 - t, t)
+    } else {
+        let angle = cos_angle.acos();
+        let sin_angle = angle.sin();
+
+        (
+            ((1.0 - t) * angle).sin() / sin_angle,
+            (t * angle).sin() / sin_angle,
+        )
+    };
+
+    normalize_quaternion(&Vector4 {
+        x: a.x * weight_a + b.x * weight_b,
+        y: a.y * weight_a + b.y * weight_b,
+        z: a.z * weight_a + b.z * weight_b,
+        w: a.w * weight_a + b.w * weight_b,
+    })
+}
+
+/// Euler angles in degrees of a quaternion, rotating around X, then Y, then Z.
+///
+/// This is Blender's XYZ order and the `Zrotation Yrotation Xrotation` channel order of BVH.
+pub fn quaternion_to_euler(quaternion: &Vector4) -> Vector3 {
+    let Vector4 { x, y, z, w } = normalize_quaternion(quaternion);
+
+    let x_angle = (2.0 * (w * x + y * z)).atan2(1.0 - 2.0 * (x * x + y * y));
+    let y_angle = (2.0 * (w * y - z * x)).clamp(-1.0, 1.0).asin();
+    let z_angle = (2.0 * (w * z + x * y)).atan2(1.0 - 2.0 * (y * y + z * z));
+
+    Vector3 {
+        x: x_angle.to_degrees(),
+        y: y_angle.to_degrees(),
+        z: z_angle.to_degrees(),
+    }
+}
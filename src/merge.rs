@@ -0,0 +1,375 @@
+use xfbin::nucc::nucc_anm::NuccAnm;
+use xfbin::nucc::{NuccStruct, NuccStructInfo, NuccStructReference};
+use xfbin::nucc_chunk::nucc_chunk_anm::{AnmClump, AnmCoord, AnmEntry, CoordParent};
+use xfbin::nucc_chunk::NuccChunkType;
+use xfbin::{xfbin::XfbinPage, Xfbin};
+
+use crate::error::StrmConvError;
+use crate::{convert_strm_xfbin, first_anmstrm_name, ConvertOptions, DmgSplit};
+
+/// Options for [`merge_anm_xfbins`].
+#[derive(Debug, Clone, Default)]
+pub struct MergeOptions {
+    /// Chunk name of the merged ANM, the chunk name of the first source when `None`.
+    pub chunk_name: Option<String>,
+}
+
+/// The result of [`merge_anm_xfbins`].
+pub struct MergeOutput {
+    /// Chunk name of the merged ANM, used for the output file name.
+    pub chunk_name: String,
+    /// The xfbin with the merged ANM page.
+    pub xfbin: Xfbin,
+    /// Entries that more than one source animates, only the first one is kept.
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// An entry that is animated by more than one source.
+#[derive(Debug, Clone)]
+pub struct MergeConflict {
+    /// Index of the source whose entry was dropped, in the order the ANMs were merged.
+    pub source: usize,
+    /// The coord in the merged ANM.
+    pub coord: AnmCoord,
+    pub entry_format: u16,
+    /// Chunk name of the animated bone, material or other entry, if it could be resolved.
+    pub chunk_name: Option<String>,
+}
+
+/// Merges every ANM of the given xfbins into a single ANM.
+///
+/// Strm xfbins are converted to an ANM (without a DMG page) first, and the main and `_dmg`
+/// ANMs of an anm xfbin are both merged. The cameras, lights and other chunks of every ANM
+/// page are kept, FCV pages are dropped.
+pub fn merge_anm_xfbins(
+    inputs: &[Xfbin],
+    options: &MergeOptions,
+) -> Result<MergeOutput, StrmConvError> {
+    let convert_options = ConvertOptions {
+        dmg_split: DmgSplit::Disabled,
+        quiet: true,
+        ..Default::default()
+    };
+
+    let converted = inputs
+        .iter()
+        .map(|input| match first_anmstrm_name(input) {
+            Some(_) => convert_strm_xfbin(input, &convert_options).map(|output| Some(output.xfbin)),
+            None => Ok(None),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let xfbins = inputs
+        .iter()
+        .zip(&converted)
+        .map(|(input, converted)| converted.as_ref().unwrap_or(input));
+
+    let mut sources: Vec<(&NuccAnm, &XfbinPage)> = Vec::new();
+    for xfbin in xfbins {
+        for page in &xfbin.pages {
+            for nucc_struct in &page.structs {
+                if let NuccChunkType::NuccChunkAnm = nucc_struct.chunk_type() {
+                    let anm = nucc_struct.downcast_ref::<NuccAnm>().ok_or_else(|| {
+                        StrmConvError::DowncastFailed {
+                            chunk_type: NuccChunkType::NuccChunkAnm.to_string(),
+                        }
+                    })?;
+
+                    sources.push((anm, page));
+                }
+            }
+        }
+    }
+
+    let (first_anm, _) = sources.first().ok_or(StrmConvError::NoAnmChunk)?;
+
+    let struct_info = NuccStructInfo {
+        chunk_name: options
+            .chunk_name
+            .clone()
+            .unwrap_or_else(|| first_anm.struct_info.chunk_name.clone()),
+        ..first_anm.struct_info.clone()
+    };
+
+    let mut merger = AnmMerger::new(struct_info.clone(), first_anm);
+    let mut merged_page = XfbinPage {
+        struct_infos: Vec::new(),
+        struct_references: Vec::new(),
+        structs: Vec::new(),
+    };
+
+    for (source, (anm, page)) in sources.iter().enumerate() {
+        merger.merge(source, anm, &page.struct_references);
+
+        for info in &page.struct_infos {
+            let is_anm = info.chunk_type == NuccChunkType::NuccChunkAnm.to_string();
+
+            if !is_anm && !merged_page.struct_infos.contains(info) {
+                merged_page.struct_infos.push(info.clone());
+
+                // Every scene chunk is copied once, with the first page that lists it
+                if let Some(nucc_struct) = page
+                    .structs
+                    .iter()
+                    .find(|nucc_struct| {
+                        crate::scene_struct_info(nucc_struct.as_ref()) == Some(info)
+                    })
+                    .and_then(|nucc_struct| crate::clone_scene_struct(nucc_struct.as_ref()))
+                {
+                    merged_page.structs.push(nucc_struct);
+                }
+            }
+        }
+    }
+
+    let (anm, struct_references, conflicts) = merger.finish();
+
+    merged_page.struct_infos.push(struct_info.clone());
+    merged_page.struct_references = struct_references;
+    merged_page
+        .structs
+        .push(Box::new(anm) as Box<dyn NuccStruct>);
+
+    let mut xfbin = Xfbin::default();
+    xfbin.pages.push(merged_page);
+
+    Ok(MergeOutput {
+        chunk_name: struct_info.chunk_name,
+        xfbin,
+        conflicts,
+    })
+}
+
+/// Unions the clumps, struct references, coord parents and entries of several ANMs.
+///
+/// Clumps, bones, materials and other entries are matched through the chunks their struct
+/// references point at, so ANMs with their own clump and reference order can be merged.
+pub struct AnmMerger {
+    anm: NuccAnm,
+    struct_references: Vec<NuccStructReference>,
+    conflicts: Vec<MergeConflict>,
+}
+
+impl AnmMerger {
+    /// Starts an empty ANM with the frame settings of `template`.
+    pub fn new(struct_info: NuccStructInfo, template: &NuccAnm) -> Self {
+        Self {
+            anm: NuccAnm {
+                struct_info,
+                clumps: Vec::new(),
+                other_entries_indices: Vec::new(),
+                unk_entry_indices: Vec::new(),
+                coord_parents: Vec::new(),
+                entries: Vec::new(),
+                ..template.clone()
+            },
+            struct_references: Vec::new(),
+            conflicts: Vec::new(),
+        }
+    }
+
+    /// Adds an ANM and the struct references of its page.
+    pub fn merge(
+        &mut self,
+        source: usize,
+        anm: &NuccAnm,
+        struct_references: &[NuccStructReference],
+    ) {
+        self.anm.frame_count = self.anm.frame_count.max(anm.frame_count);
+
+        // Resolve the clumps first so every coord of the source can be remapped
+        let clump_indices: Vec<Option<usize>> = anm
+            .clumps
+            .iter()
+            .map(|clump| self.merge_clump(clump, struct_references))
+            .collect();
+
+        let remap_coord = |merger: &mut Self, coord: &AnmCoord| -> Option<AnmCoord> {
+            if coord.clump_index < 0 {
+                let index = *anm.other_entries_indices.get(coord.coord_index as usize)?;
+                let reference = struct_references.get(index as usize)?;
+                let index = merger.add_reference(reference);
+
+                let coord_index = match merger
+                    .anm
+                    .other_entries_indices
+                    .iter()
+                    .position(|other| *other == index)
+                {
+                    Some(coord_index) => coord_index,
+                    None => {
+                        merger.anm.other_entries_indices.push(index);
+                        merger.anm.other_entries_indices.len() - 1
+                    }
+                };
+
+                return Some(AnmCoord {
+                    clump_index: coord.clump_index,
+                    coord_index: u16::try_from(coord_index).ok()?,
+                });
+            }
+
+            let clump_index = (*clump_indices.get(coord.clump_index as usize)?)?;
+            let clump = anm.clumps.get(coord.clump_index as usize)?;
+            let index = *clump
+                .bone_material_indices
+                .get(coord.coord_index as usize)?;
+            let index = merger.add_reference(struct_references.get(index as usize)?);
+
+            let coord_index = merger.anm.clumps[clump_index]
+                .bone_material_indices
+                .iter()
+                .position(|bone| *bone == index)?;
+
+            Some(AnmCoord {
+                clump_index: i16::try_from(clump_index).ok()?,
+                coord_index: u16::try_from(coord_index).ok()?,
+            })
+        };
+
+        for coord_parent in &anm.coord_parents {
+            let coord_parent = match (
+                remap_coord(self, &coord_parent.parent),
+                remap_coord(self, &coord_parent.child),
+            ) {
+                (Some(parent), Some(child)) => CoordParent { parent, child },
+                _ => continue,
+            };
+
+            if !self.anm.coord_parents.contains(&coord_parent) {
+                self.anm.coord_parents.push(coord_parent);
+            }
+        }
+
+        for entry in &anm.entries {
+            let coord = match remap_coord(self, &entry.coord) {
+                Some(coord) => coord,
+                None => continue,
+            };
+
+            let exists =
+                self.anm.entries.iter().any(|merged| {
+                    merged.coord == coord && merged.entry_format == entry.entry_format
+                });
+
+            if exists {
+                let chunk_name = self
+                    .coord_reference(&coord)
+                    .map(|reference| reference.struct_info.chunk_name.clone());
+
+                self.conflicts.push(MergeConflict {
+                    source,
+                    coord,
+                    entry_format: entry.entry_format,
+                    chunk_name,
+                });
+                continue;
+            }
+
+            self.anm.entries.push(AnmEntry {
+                coord,
+                ..entry.clone()
+            });
+        }
+    }
+
+    /// Returns the merged ANM, its struct references and the conflicting entries.
+    pub fn finish(mut self) -> (NuccAnm, Vec<NuccStructReference>, Vec<MergeConflict>) {
+        self.anm.entries.sort_by(|a, b| {
+            a.coord
+                .clump_index
+                .cmp(&b.coord.clump_index)
+                .then_with(|| a.coord.coord_index.cmp(&b.coord.coord_index))
+                .then_with(|| a.entry_format.cmp(&b.entry_format))
+        });
+
+        (self.anm, self.struct_references, self.conflicts)
+    }
+
+    /// Finds or adds the clump and adds the bones, materials and models it's missing.
+    fn merge_clump(
+        &mut self,
+        clump: &AnmClump,
+        struct_references: &[NuccStructReference],
+    ) -> Option<usize> {
+        let clump_reference =
+            self.add_reference(struct_references.get(clump.clump_index as usize)?);
+
+        let clump_index = match self
+            .anm
+            .clumps
+            .iter()
+            .position(|merged| merged.clump_index == clump_reference)
+        {
+            Some(clump_index) => clump_index,
+            None => {
+                self.anm.clumps.push(AnmClump {
+                    clump_index: clump_reference,
+                    bone_material_indices: Vec::new(),
+                    model_indices: Vec::new(),
+                });
+                self.anm.clumps.len() - 1
+            }
+        };
+
+        for index in &clump.bone_material_indices {
+            if let Some(reference) = struct_references.get(*index as usize) {
+                let index = self.add_reference(reference);
+                let bones = &mut self.anm.clumps[clump_index].bone_material_indices;
+
+                if !bones.contains(&index) {
+                    bones.push(index);
+                }
+            }
+        }
+
+        for index in &clump.model_indices {
+            if let Some(reference) = struct_references.get(*index as usize) {
+                let index = self.add_reference(reference);
+                let models = &mut self.anm.clumps[clump_index].model_indices;
+
+                if !models.contains(&index) {
+                    models.push(index);
+                }
+            }
+        }
+
+        Some(clump_index)
+    }
+
+    /// Returns the index of the struct reference, adding it if no reference points at its chunk.
+    fn add_reference(&mut self, reference: &NuccStructReference) -> u32 {
+        let index = match self
+            .struct_references
+            .iter()
+            .position(|merged| merged.struct_info == reference.struct_info)
+        {
+            Some(index) => index,
+            None => {
+                self.struct_references.push(reference.clone());
+                self.struct_references.len() - 1
+            }
+        };
+
+        index as u32
+    }
+
+    /// The struct reference a merged coord animates.
+    fn coord_reference(&self, coord: &AnmCoord) -> Option<&NuccStructReference> {
+        let index = if coord.clump_index < 0 {
+            *self
+                .anm
+                .other_entries_indices
+                .get(coord.coord_index as usize)?
+        } else {
+            *self
+                .anm
+                .clumps
+                .get(coord.clump_index as usize)?
+                .bone_material_indices
+                .get(coord.coord_index as usize)?
+        };
+
+        self.struct_references.get(index as usize)
+    }
+}
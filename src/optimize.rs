@@ -0,0 +1,261 @@
+use xfbin::nucc_chunk::nucc_chunk_anm::{AnmCurveFormat, AnmEntry, AnmEntryFormat, Curve};
+use xfbin::nucc_chunk::nucc_helper::*;
+
+use crate::math::*;
+use crate::sample::decompress_quaternion;
+
+/// Tolerances for reducing bone curves.
+///
+/// Location, rotation and scale keys that can be interpolated from their neighbours are
+/// removed. Toggles are stored once per frame without a keyed format, so they are only
+/// collapsed to a single value when they don't change.
+#[derive(Debug, Clone)]
+pub struct KeyframeReduction {
+    /// Maximum distance between a removed location key and the interpolated location.
+    pub translation_tolerance: f32,
+    /// Maximum angle in degrees between a removed rotation and the interpolated rotation.
+    pub rotation_tolerance: f32,
+    /// Maximum distance between a removed scale key and the interpolated scale.
+    pub scale_tolerance: f32,
+}
+
+impl Default for KeyframeReduction {
+    fn default() -> Self {
+        Self {
+            translation_tolerance: 0.01,
+            rotation_tolerance: 0.1,
+            scale_tolerance: 0.001,
+        }
+    }
+}
+
+/// How much the keyframe reduction and the constant curve collapsing saved.
+#[derive(Debug, Clone, Default)]
+pub struct ReductionReport {
+    pub keyframes_before: usize,
+    pub keyframes_after: usize,
+    /// Bone curves that were reduced to a single key or value.
+    pub static_curves: usize,
+    /// Curves that held the same value on every frame and were written as a single key.
+    pub constant_curves: usize,
+}
+
+impl ReductionReport {
+    /// Percentage of keyframes that were removed.
+    pub fn saved_percent(&self) -> f32 {
+        if self.keyframes_before == 0 {
+            return 0.0;
+        }
+
+        100.0 * (self.keyframes_before - self.keyframes_after) as f32 / self.keyframes_before as f32
+    }
+
+    fn add(&mut self, before: usize, after: usize) {
+        self.keyframes_before += before;
+        self.keyframes_after += after;
+
+        if before > 1 && after == 1 {
+            self.static_curves += 1;
+        }
+    }
+}
+
+/// Reduces the location, rotation, scale and toggle curves of a bone entry.
+///
+/// Location and scale keys are removed when linear interpolation between the kept keys stays
+/// within the tolerance. Per-frame rotations are rewritten as keyed `INT1_FLOAT4` rotations
+/// with the frames slerp can rebuild within the rotation tolerance removed, or a single key
+/// when every frame is within the tolerance of the first one. They stay per-frame when the
+/// keys would take more space than the frames. Toggles are stored once per frame, so a toggle
+/// curve is only reduced to a constant `FLOAT1` value when every frame is equal. This has to
+/// run before the null keyframes are appended and the curve headers are sized.
+pub fn reduce_bone_curves(
+    anm_entry: &mut AnmEntry,
+    reduction: &KeyframeReduction,
+    frame_ticks: i32,
+    report: &mut ReductionReport,
+) {
+    if anm_entry.entry_format != AnmEntryFormat::BONE as u16 {
+        return;
+    }
+
+    for (curve_index, (curve, curve_header)) in anm_entry
+        .curves
+        .iter_mut()
+        .zip(&mut anm_entry.curve_headers)
+        .enumerate()
+        .take(4)
+    {
+        match curve {
+            Curve::KeyframeVector3(keyframes) => {
+                let tolerance = if curve_index == 0 {
+                    reduction.translation_tolerance
+                } else {
+                    reduction.scale_tolerance
+                };
+
+                let before = keyframes.len();
+                reduce_vector3_keyframes(keyframes, tolerance);
+                report.add(before, keyframes.len());
+            }
+            Curve::QuaternionShort(values) => {
+                let before = values.len();
+                let keyframes =
+                    reduce_quaternions(values, reduction.rotation_tolerance, frame_ticks);
+
+                // A float quaternion key is 20 bytes, a short quaternion frame 8
+                if before > 1 && keyframes.len() * 5 < before * 2 {
+                    report.add(before, keyframes.len());
+                    *curve = Curve::KeyframeVector4(keyframes);
+                    curve_header.curve_format = AnmCurveFormat::INT1_FLOAT4 as u16;
+                } else {
+                    report.add(before, before);
+                }
+            }
+            Curve::Float(values) if curve_index == 3 => {
+                let before = values.len();
+                if is_constant(values) {
+                    values.truncate(1);
+                    curve_header.curve_format = AnmCurveFormat::FLOAT1 as u16;
+                }
+                report.add(before, values.len());
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Greedily keeps the fewest keys so every removed key is within `tolerance` of the
+/// interpolation between the kept keys around it. The first and last keys are always kept.
+fn reduce_vector3_keyframes(keyframes: &mut Vec<KeyframeVector3>, tolerance: f32) {
+    reduce_keyframes(
+        keyframes,
+        |keyframe| keyframe.frame,
+        |from, to, middle, t| {
+            vector3_distance(&vector3_lerp(&from.value, &to.value, t), &middle.value) <= tolerance
+        },
+    );
+}
+
+/// Keyed rotations of a per-frame rotation curve, with the keys that slerp between the kept
+/// keys rebuilds within `tolerance` degrees removed. A curve that stays within `tolerance` of
+/// its first frame becomes a single key.
+fn reduce_quaternions(
+    values: &[QuaternionShort],
+    tolerance: f32,
+    frame_ticks: i32,
+) -> Vec<KeyframeVector4> {
+    let mut keyframes: Vec<KeyframeVector4> = values
+        .iter()
+        .enumerate()
+        .map(|(frame, value)| KeyframeVector4 {
+            frame: frame as i32 * frame_ticks,
+            value: normalize_quaternion(&decompress_quaternion(value)),
+        })
+        .collect();
+
+    let is_static = keyframes
+        .iter()
+        .all(|keyframe| quaternion_angle(&keyframes[0].value, &keyframe.value) <= tolerance);
+
+    if is_static {
+        keyframes.truncate(1);
+    } else {
+        reduce_keyframes(
+            &mut keyframes,
+            |keyframe| keyframe.frame,
+            |from, to, middle, t| {
+                quaternion_angle(&quaternion_slerp(&from.value, &to.value, t), &middle.value)
+                    <= tolerance
+            },
+        );
+    }
+
+    keyframes
+}
+
+/// Greedily keeps the fewest keys so `fits` holds for every removed key, given the kept keys
+/// around it and how far it is between them. The first and last keys are always kept.
+fn reduce_keyframes<T: Clone>(
+    keyframes: &mut Vec<T>,
+    frame: impl Fn(&T) -> i32,
+    fits: impl Fn(&T, &T, &T, f32) -> bool,
+) {
+    if keyframes.len() < 3 {
+        return;
+    }
+
+    let mut kept: Vec<T> = vec![keyframes[0].clone()];
+    let mut anchor = 0;
+
+    for end in 2..keyframes.len() {
+        let fits = (anchor + 1..end).all(|middle| {
+            let from = &keyframes[anchor];
+            let to = &keyframes[end];
+            let t =
+                (frame(&keyframes[middle]) - frame(from)) as f32 / (frame(to) - frame(from)) as f32;
+
+            fits(from, to, &keyframes[middle], t)
+        });
+
+        if !fits {
+            anchor = end - 1;
+            kept.push(keyframes[anchor].clone());
+        }
+    }
+
+    if let Some(last) = keyframes.last() {
+        kept.push(last.clone());
+    }
+
+    *keyframes = kept;
+}
+
+/// Writes curves that hold the same value on every frame with a single key.
+///
+/// Per-frame floats switch from `FLOAT1ALT` to the constant `FLOAT1` format, keyed floats
+/// (like the material colors) become a constant `FLOAT1` curve and keyed vectors keep only
+/// their first key. This has to run before the null keyframes are appended and the curve
+/// headers are sized.
+pub fn collapse_constant_curves(anm_entry: &mut AnmEntry, report: &mut ReductionReport) {
+    for (curve, curve_header) in anm_entry
+        .curves
+        .iter_mut()
+        .zip(&mut anm_entry.curve_headers)
+    {
+        let collapsed = match curve {
+            Curve::Float(values)
+                if curve_header.curve_format == AnmCurveFormat::FLOAT1ALT as u16
+                    && is_constant(values) =>
+            {
+                values.truncate(1);
+                curve_header.curve_format = AnmCurveFormat::FLOAT1 as u16;
+                true
+            }
+            Curve::KeyframeFloat(keyframes)
+                if is_constant(&keyframes.iter().map(|key| key.value).collect::<Vec<_>>()) =>
+            {
+                let value = keyframes[0].value;
+                *curve = Curve::Float(vec![value]);
+                curve_header.curve_format = AnmCurveFormat::FLOAT1 as u16;
+                true
+            }
+            Curve::KeyframeVector3(keyframes)
+                if is_constant(&keyframes.iter().map(|key| &key.value).collect::<Vec<_>>()) =>
+            {
+                keyframes.truncate(1);
+                true
+            }
+            _ => false,
+        };
+
+        if collapsed {
+            report.constant_curves += 1;
+        }
+    }
+}
+
+/// Whether there is more than one value and all of them are equal.
+fn is_constant<T: PartialEq>(values: &[T]) -> bool {
+    values.len() > 1 && values.iter().all(|value| *value == values[0])
+}
@@ -0,0 +1,259 @@
+use xfbin::nucc::nucc_anm::NuccAnm;
+use xfbin::nucc::{NuccStructInfo, NuccStructReference};
+use xfbin::nucc_chunk::nucc_chunk_anm::{AnmClump, AnmCoord, AnmEntry, CoordParent};
+
+/// Maps the clumps and struct references of an ANM to their indices after some clumps are removed.
+///
+/// A removed clump takes its clump, bone/material and model references with it, unless a kept
+/// clump uses the same reference. Every other reference keeps its place relative to the rest.
+#[derive(Debug, Clone)]
+pub struct ClumpRemap {
+    clumps: Vec<Option<usize>>,
+    references: Vec<Option<u32>>,
+}
+
+impl ClumpRemap {
+    /// Builds the table for removing the clumps at `removed_clumps` from `clumps`, where the
+    /// clumps point into a page with `reference_count` struct references.
+    pub fn new(clumps: &[AnmClump], reference_count: usize, removed_clumps: &[usize]) -> Self {
+        let mut next_clump = 0;
+        let clumps_map = (0..clumps.len())
+            .map(|i| {
+                if removed_clumps.contains(&i) {
+                    None
+                } else {
+                    next_clump += 1;
+                    Some(next_clump - 1)
+                }
+            })
+            .collect();
+
+        let mut removed_references = vec![false; reference_count];
+        for &i in removed_clumps {
+            if let Some(clump) = clumps.get(i) {
+                for &index in clump_references(clump) {
+                    if let Some(removed) = removed_references.get_mut(index as usize) {
+                        *removed = true;
+                    }
+                }
+            }
+        }
+
+        // References shared with a kept clump stay
+        for (i, clump) in clumps.iter().enumerate() {
+            if !removed_clumps.contains(&i) {
+                for &index in clump_references(clump) {
+                    if let Some(removed) = removed_references.get_mut(index as usize) {
+                        *removed = false;
+                    }
+                }
+            }
+        }
+
+        let mut next_reference = 0;
+        let references_map = removed_references
+            .into_iter()
+            .map(|removed| {
+                if removed {
+                    None
+                } else {
+                    next_reference += 1;
+                    Some(next_reference - 1)
+                }
+            })
+            .collect();
+
+        Self {
+            clumps: clumps_map,
+            references: references_map,
+        }
+    }
+
+    /// Also removes the struct references at these indices, like the other entries of an ANM.
+    pub fn remove_references(&mut self, indices: &[u32]) {
+        for &index in indices {
+            if let Some(reference) = self.references.get_mut(index as usize) {
+                *reference = None;
+            }
+        }
+
+        for (next_reference, reference) in self.references.iter_mut().flatten().enumerate() {
+            *reference = next_reference as u32;
+        }
+    }
+
+    /// The new index of a clump, `None` when it was removed.
+    pub fn clump(&self, index: usize) -> Option<usize> {
+        self.clumps.get(index).copied().flatten()
+    }
+
+    /// The new index of a struct reference, `None` when it was removed.
+    pub fn reference(&self, index: u32) -> Option<u32> {
+        self.references.get(index as usize).copied().flatten()
+    }
+
+    /// The coord with its new clump index, `None` when its clump was removed.
+    ///
+    /// Coords that don't belong to a clump (a negative clump index) are kept as they are.
+    pub fn coord(&self, coord: &AnmCoord) -> Option<AnmCoord> {
+        if coord.clump_index < 0 {
+            return Some(coord.clone());
+        }
+
+        let clump_index = self.clump(coord.clump_index as usize)?;
+
+        Some(AnmCoord {
+            clump_index: i16::try_from(clump_index).ok()?,
+            coord_index: coord.coord_index,
+        })
+    }
+
+    /// The kept clumps with their struct reference indices remapped.
+    pub fn remap_clumps(&self, clumps: &[AnmClump]) -> Vec<AnmClump> {
+        let remap_indices = |indices: &[u32]| {
+            indices
+                .iter()
+                .filter_map(|&index| self.reference(index))
+                .collect()
+        };
+
+        clumps
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| self.clump(*i).is_some())
+            .filter_map(|(_, clump)| {
+                Some(AnmClump {
+                    clump_index: self.reference(clump.clump_index)?,
+                    bone_material_indices: remap_indices(&clump.bone_material_indices),
+                    model_indices: remap_indices(&clump.model_indices),
+                })
+            })
+            .collect()
+    }
+
+    /// The coord parents whose parent and child are both kept, with their clump indices remapped.
+    pub fn remap_coord_parents(&self, coord_parents: &[CoordParent]) -> Vec<CoordParent> {
+        coord_parents
+            .iter()
+            .filter_map(|coord_parent| {
+                Some(CoordParent {
+                    parent: self.coord(&coord_parent.parent)?,
+                    child: self.coord(&coord_parent.child)?,
+                })
+            })
+            .collect()
+    }
+
+    /// The entries of the kept clumps with their clump indices remapped.
+    pub fn remap_entries(&self, entries: &[AnmEntry]) -> Vec<AnmEntry> {
+        entries
+            .iter()
+            .filter_map(|entry| {
+                Some(AnmEntry {
+                    coord: self.coord(&entry.coord)?,
+                    ..entry.clone()
+                })
+            })
+            .collect()
+    }
+
+    /// Reference indices (like the other entries of an ANM) that are kept, remapped.
+    pub fn remap_reference_indices(&self, indices: &[u32]) -> Vec<u32> {
+        indices
+            .iter()
+            .filter_map(|&index| self.reference(index))
+            .collect()
+    }
+
+    /// The kept struct references of the page.
+    pub fn remap_references<T: Clone>(&self, struct_references: &[T]) -> Vec<T> {
+        struct_references
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| self.reference(*i as u32).is_some())
+            .map(|(_, reference)| reference.clone())
+            .collect()
+    }
+}
+
+/// Keeps only the struct references an ANM points at and remaps its indices to them.
+///
+/// References are kept in their original order, so clumps keep their bones and materials
+/// in the same order, and references to the same chunk are merged into the first one.
+pub fn retain_used_references(anm: &mut NuccAnm, struct_references: &mut Vec<NuccStructReference>) {
+    let mut used = vec![false; struct_references.len()];
+    for &index in anm
+        .clumps
+        .iter()
+        .flat_map(clump_references)
+        .chain(&anm.other_entries_indices)
+    {
+        if let Some(used) = used.get_mut(index as usize) {
+            *used = true;
+        }
+    }
+
+    let mut kept_references: Vec<NuccStructReference> = Vec::new();
+    let references_map: Vec<Option<u32>> = struct_references
+        .iter()
+        .zip(used)
+        .map(|(reference, used)| {
+            if !used {
+                return None;
+            }
+
+            let index = match kept_references
+                .iter()
+                .position(|kept| kept.struct_info == reference.struct_info)
+            {
+                Some(index) => index,
+                None => {
+                    kept_references.push(reference.clone());
+                    kept_references.len() - 1
+                }
+            };
+
+            Some(index as u32)
+        })
+        .collect();
+
+    let remap = |index: &mut u32| {
+        if let Some(Some(new_index)) = references_map.get(*index as usize) {
+            *index = *new_index;
+        }
+    };
+
+    for clump in &mut anm.clumps {
+        remap(&mut clump.clump_index);
+        clump.bone_material_indices.iter_mut().for_each(remap);
+        clump.model_indices.iter_mut().for_each(remap);
+    }
+    anm.other_entries_indices.iter_mut().for_each(remap);
+
+    *struct_references = kept_references;
+}
+
+/// Drops the struct infos of a page that only removed struct references pointed at.
+///
+/// Struct infos that no reference points at, like the ANM itself, are kept.
+pub fn retain_referenced_struct_infos(
+    struct_infos: &mut Vec<NuccStructInfo>,
+    struct_references: &[NuccStructReference],
+    kept_references: &[NuccStructReference],
+) {
+    struct_infos.retain(|info| {
+        kept_references
+            .iter()
+            .any(|reference| reference.struct_info == *info)
+            || !struct_references
+                .iter()
+                .any(|reference| reference.struct_info == *info)
+    });
+}
+
+/// Every struct reference index a clump points at.
+fn clump_references(clump: &AnmClump) -> impl Iterator<Item = &u32> {
+    std::iter::once(&clump.clump_index)
+        .chain(&clump.bone_material_indices)
+        .chain(&clump.model_indices)
+}
@@ -0,0 +1,156 @@
+use xfbin::nucc_chunk::nucc_chunk_anm::{AnmEntry, AnmEntryFormat, Curve};
+use xfbin::nucc_chunk::nucc_helper::*;
+
+use crate::converter::compress_quaternion;
+use crate::math::quaternion_slerp;
+use crate::sample::{decompress_quaternion, sample_float, sample_vector3};
+
+/// Stretches the timing of an entry by `time_scale` and resamples its curves.
+///
+/// A scale of 2 plays the animation at half speed, or keeps its speed when going from 30 to
+/// 60 fps. Keyed curves are sampled once per new frame, curves with one value per frame are
+/// interpolated between their frames, linearly for floats and colors and with slerp for
+/// rotations. Bone toggles are switches, so they hold the value of the frame before instead.
+/// Material colors keep their second key half a frame after each frame. This has to run before
+/// the null keyframes are appended and the curve headers are sized.
+pub fn resample_entry(anm_entry: &mut AnmEntry, time_scale: f32, frame_ticks: i32) {
+    let half_frame_keys = anm_entry.entry_format == AnmEntryFormat::MATERIAL as u16;
+    let is_bone = anm_entry.entry_format == AnmEntryFormat::BONE as u16;
+
+    for (curve_index, curve) in anm_entry.curves.iter_mut().enumerate() {
+        let resampled = match &*curve {
+            Curve::KeyframeVector3(keyframes) if keyframes.len() > 1 => {
+                let last_tick = keyframes.last().map_or(0, |keyframe| keyframe.frame);
+
+                Curve::KeyframeVector3(
+                    resampled_ticks(last_tick, time_scale, frame_ticks, false)
+                        .filter_map(|(tick, source_tick)| {
+                            sample_vector3(curve, source_tick, frame_ticks)
+                                .map(|value| KeyframeVector3 { frame: tick, value })
+                        })
+                        .collect(),
+                )
+            }
+            Curve::KeyframeFloat(keyframes) if keyframes.len() > 1 => {
+                let last_tick = keyframes.last().map_or(0, |keyframe| keyframe.frame);
+
+                Curve::KeyframeFloat(
+                    resampled_ticks(last_tick, time_scale, frame_ticks, half_frame_keys)
+                        .filter_map(|(tick, source_tick)| {
+                            sample_float(curve, source_tick, frame_ticks)
+                                .map(|value| KeyframeFloat { frame: tick, value })
+                        })
+                        .collect(),
+                )
+            }
+            Curve::Float(values) if is_bone && curve_index == 3 => {
+                // Frames that land on a source frame can be a rounding error short of it
+                Curve::Float(resample_frames(values, time_scale, |a, b, t| {
+                    if t < 0.999 {
+                        *a
+                    } else {
+                        *b
+                    }
+                }))
+            }
+            Curve::Float(values) => Curve::Float(resample_frames(values, time_scale, |a, b, t| {
+                a + (b - a) * t
+            })),
+            Curve::RGB(values) => Curve::RGB(resample_frames(values, time_scale, |a, b, t| {
+                let lerp = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+
+                RGB {
+                    r: lerp(a.r, b.r),
+                    g: lerp(a.g, b.g),
+                    b: lerp(a.b, b.b),
+                }
+            })),
+            Curve::QuaternionShort(values) => {
+                let rotations = values.iter().map(decompress_quaternion).collect::<Vec<_>>();
+                let mut previous_rotation: Option<Vector4> = None;
+
+                Curve::QuaternionShort(
+                    resample_frames(&rotations, time_scale, quaternion_slerp)
+                        .iter()
+                        .map(|rotation| compress_quaternion(rotation, &mut previous_rotation))
+                        .collect(),
+                )
+            }
+            _ => {
+                // A single key only needs its time moved
+                if let Curve::KeyframeVector3(keyframes) = curve {
+                    for keyframe in keyframes.iter_mut() {
+                        keyframe.frame = scale_tick(keyframe.frame, time_scale, frame_ticks);
+                    }
+                } else if let Curve::KeyframeFloat(keyframes) = curve {
+                    for keyframe in keyframes.iter_mut() {
+                        keyframe.frame = scale_tick(keyframe.frame, time_scale, frame_ticks);
+                    }
+                }
+                continue;
+            }
+        };
+
+        *curve = resampled;
+    }
+}
+
+/// Scales the frame count of an ANM, rounded to whole frames.
+pub fn resample_frame_count(frame_count: u32, time_scale: f32, frame_ticks: i32) -> u32 {
+    scale_tick(frame_count as i32, time_scale, frame_ticks).max(0) as u32
+}
+
+/// The tick of every new frame up to the scaled `last_tick`, with the tick it's sampled from.
+///
+/// With `half_frame_keys` every frame gets a second key half a frame later, sampled from the
+/// same tick, like the material colors of converted ANMSTRMs.
+fn resampled_ticks(
+    last_tick: i32,
+    time_scale: f32,
+    frame_ticks: i32,
+    half_frame_keys: bool,
+) -> impl Iterator<Item = (i32, i32)> {
+    // The last key can be a half frame key, the frames are counted up to the frame before it
+    let last_frame_tick = last_tick / frame_ticks * frame_ticks;
+    let last_frame = scale_tick(last_frame_tick, time_scale, frame_ticks) / frame_ticks;
+
+    (0..=last_frame).flat_map(move |frame| {
+        let tick = frame * frame_ticks;
+        let source_tick = (tick as f32 / time_scale).round() as i32;
+
+        let half_frame_key = half_frame_keys.then_some((tick + frame_ticks / 2, source_tick));
+
+        std::iter::once((tick, source_tick)).chain(half_frame_key)
+    })
+}
+
+/// Interpolates a curve with one value per frame at every frame of the scaled curve.
+///
+/// Curves with a single value are constant and are kept as they are.
+fn resample_frames<T: Clone>(
+    values: &[T],
+    time_scale: f32,
+    interpolate: impl Fn(&T, &T, f32) -> T,
+) -> Vec<T> {
+    if values.len() < 2 {
+        return values.to_vec();
+    }
+
+    let last = values.len() - 1;
+    let frame_count = (last as f32 * time_scale).round() as usize + 1;
+
+    (0..frame_count)
+        .map(|frame| {
+            let source_frame = frame as f32 / time_scale;
+            let from = (source_frame.floor() as usize).min(last);
+            let to = (from + 1).min(last);
+
+            interpolate(&values[from], &values[to], source_frame - from as f32)
+        })
+        .collect()
+}
+
+/// Scales a tick and rounds it to a whole frame.
+fn scale_tick(tick: i32, time_scale: f32, frame_ticks: i32) -> i32 {
+    (tick as f32 * time_scale / frame_ticks as f32).round() as i32 * frame_ticks
+}
@@ -0,0 +1,393 @@
+use xfbin::nucc::nucc_anm::NuccAnm;
+use xfbin::nucc::nucc_anmstrm::NuccAnmStrm;
+use xfbin::nucc::nucc_anmstrmframe::NuccAnmStrmFrame;
+use xfbin::nucc::{NuccStruct, NuccStructInfo, NuccStructReference};
+use xfbin::nucc_chunk::nucc_chunk_anm::{AnmCoord, AnmEntry, AnmEntryFormat};
+use xfbin::nucc_chunk::nucc_chunk_anmstrm::AnmStrmClump;
+use xfbin::nucc_chunk::nucc_chunk_anmstrmframe::*;
+use xfbin::nucc_chunk::nucc_helper::*;
+use xfbin::nucc_chunk::NuccChunkType;
+use xfbin::{xfbin::XfbinPage, Xfbin};
+
+use crate::converter::entry_format;
+use crate::error::StrmConvError;
+use crate::sample::*;
+
+/// Options for [`convert_anm_xfbin`].
+#[derive(Debug, Clone)]
+pub struct ReverseOptions {
+    /// Chunk name of the ANM to convert, the first ANM that isn't a DMG page when `None`.
+    pub chunk_name: Option<String>,
+    /// Whether to merge the `<chunk_name>_dmg` ANM back into the ANMSTRM.
+    pub merge_dmg: bool,
+    /// Number of keyframe ticks in one frame.
+    pub frame_ticks: i32,
+}
+
+impl Default for ReverseOptions {
+    fn default() -> Self {
+        Self {
+            chunk_name: None,
+            merge_dmg: true,
+            frame_ticks: FRAME_TICKS,
+        }
+    }
+}
+
+/// The result of [`convert_anm_xfbin`].
+pub struct ReverseOutput {
+    /// Chunk name of the ANMSTRM, used for the output file name.
+    pub chunk_name: String,
+    /// The xfbin with the ANMSTRM page followed by one page per frame.
+    pub xfbin: Xfbin,
+    /// Whether a DMG ANM was merged into the ANMSTRM.
+    pub merged_dmg: bool,
+}
+
+/// Converts the ANM of an anm xfbin back into an ANMSTRM with one ANMSTRM frame per frame.
+///
+/// The cameras, lights and other chunks of the ANM page are kept, FCV pages are dropped.
+pub fn convert_anm_xfbin(
+    input: &Xfbin,
+    options: &ReverseOptions,
+) -> Result<ReverseOutput, StrmConvError> {
+    let (page_index, anm) = find_anm(input, |anm| match &options.chunk_name {
+        Some(chunk_name) => anm.struct_info.chunk_name == *chunk_name,
+        None => !anm.struct_info.chunk_name.ends_with("_dmg"),
+    })?
+    .ok_or_else(|| match &options.chunk_name {
+        Some(chunk_name) => StrmConvError::NoStructInfo {
+            chunk_name: chunk_name.clone(),
+        },
+        None => StrmConvError::NoAnmChunk,
+    })?;
+
+    let page = &input.pages[page_index];
+    let chunk_name = anm.struct_info.chunk_name.clone();
+
+    let mut anm = anm.clone();
+    let mut merged_dmg = false;
+
+    if options.merge_dmg {
+        let dmg_chunk_name = chunk_name.clone() + "_dmg";
+
+        if let Some((dmg_page_index, dmg_anm)) =
+            find_anm(input, |anm| anm.struct_info.chunk_name == dmg_chunk_name)?
+        {
+            merge_dmg_anm(
+                &mut anm,
+                &page.struct_references,
+                dmg_anm,
+                &input.pages[dmg_page_index].struct_references,
+            );
+            merged_dmg = true;
+        }
+    }
+
+    let anmstrm_info = NuccStructInfo {
+        chunk_type: NuccChunkType::NuccChunkAnmStrm.to_string(),
+        ..anm.struct_info.clone()
+    };
+
+    let (anmstrm, anmstrmframes) = convert_anm(&anm, &anmstrm_info, options.frame_ticks);
+
+    let mut anmstrm_page = XfbinPage {
+        struct_infos: page
+            .struct_infos
+            .iter()
+            .filter(|struct_info| struct_info.chunk_type != NuccChunkType::NuccChunkAnm.to_string())
+            .cloned()
+            .collect(),
+        struct_references: page.struct_references.clone(),
+        structs: Vec::new(),
+    };
+    anmstrm_page.struct_infos.push(anmstrm_info);
+
+    for nucc_struct in &page.structs {
+        if let Some(nucc_struct) = crate::clone_scene_struct(nucc_struct.as_ref()) {
+            anmstrm_page.structs.push(nucc_struct);
+        }
+    }
+    anmstrm_page
+        .structs
+        .push(Box::new(anmstrm) as Box<dyn NuccStruct>);
+
+    let mut xfbin = Xfbin::default();
+    xfbin.pages.push(anmstrm_page);
+
+    for anmstrmframe in anmstrmframes {
+        xfbin.pages.push(XfbinPage {
+            struct_infos: vec![anmstrmframe.struct_info.clone()],
+            struct_references: Vec::new(),
+            structs: vec![Box::new(anmstrmframe) as Box<dyn NuccStruct>],
+        });
+    }
+
+    Ok(ReverseOutput {
+        chunk_name,
+        xfbin,
+        merged_dmg,
+    })
+}
+
+/// Samples every entry of an ANM at every frame of `frame_ticks` ticks and builds the ANMSTRM
+/// and its frames.
+pub fn convert_anm(
+    anm: &NuccAnm,
+    struct_info: &NuccStructInfo,
+    frame_ticks: i32,
+) -> (NuccAnmStrm, Vec<NuccAnmStrmFrame>) {
+    let frame_ticks = frame_ticks.max(1);
+    let frame_count = anm.frame_count as i32 / frame_ticks + 1;
+
+    let anmstrmframes = (0..frame_count)
+        .map(|frame| {
+            let tick = frame * frame_ticks;
+
+            let entries = anm
+                .entries
+                .iter()
+                .filter_map(|anm_entry| {
+                    sample_entry(anm_entry, tick, frame_ticks).map(|entry_data| AnmStrmEntry {
+                        coord: anm_entry.coord.clone(),
+                        entry_format: entry_format(&entry_data).unwrap_or_default(),
+                        entry_data,
+                    })
+                })
+                .collect();
+
+            NuccAnmStrmFrame {
+                struct_info: NuccStructInfo {
+                    chunk_type: NuccChunkType::NuccChunkAnmStrmFrame.to_string(),
+                    ..struct_info.clone()
+                },
+                frame: tick as u32,
+                entries,
+                ..Default::default()
+            }
+        })
+        .collect();
+
+    let anmstrm = NuccAnmStrm {
+        struct_info: struct_info.clone(),
+        version: anm.version,
+        frame_count: anm.frame_count + frame_ticks as u32,
+        // The ticks between two stored frames
+        frame_size: frame_ticks as u32,
+        is_looped: anm.is_looped,
+        clumps: anm
+            .clumps
+            .iter()
+            .map(|clump| AnmStrmClump {
+                clump_index: clump.clump_index,
+                bone_material_indices: clump.bone_material_indices.clone(),
+                model_indices: clump.model_indices.clone(),
+            })
+            .collect(),
+        other_entry_indices: anm.other_entries_indices.clone(),
+        coord_parents: anm.coord_parents.clone(),
+    };
+
+    (anmstrm, anmstrmframes)
+}
+
+/// Adds the entries of a DMG ANM back to the ANM it was split from.
+///
+/// Clumps and bones are matched through the chunks their struct references point at, since
+/// the DMG page has its own clump and reference order. Entries the ANM already has are kept.
+pub fn merge_dmg_anm(
+    anm: &mut NuccAnm,
+    struct_references: &[NuccStructReference],
+    dmg_anm: &NuccAnm,
+    dmg_struct_references: &[NuccStructReference],
+) {
+    for dmg_entry in &dmg_anm.entries {
+        let coord = match remap_coord(
+            &dmg_entry.coord,
+            dmg_anm,
+            dmg_struct_references,
+            anm,
+            struct_references,
+        ) {
+            Some(coord) => coord,
+            None => continue,
+        };
+
+        let exists = anm
+            .entries
+            .iter()
+            .any(|entry| entry.coord == coord && entry.entry_format == dmg_entry.entry_format);
+
+        if !exists {
+            anm.entries.push(AnmEntry {
+                coord,
+                ..dmg_entry.clone()
+            });
+        }
+    }
+
+    anm.entries.sort_by(|a, b| {
+        a.coord
+            .clump_index
+            .cmp(&b.coord.clump_index)
+            .then_with(|| a.coord.coord_index.cmp(&b.coord.coord_index))
+            .then_with(|| a.entry_format.cmp(&b.entry_format))
+    });
+}
+
+/// Finds the coord of another ANM that points at the same clump and bone or material chunk.
+fn remap_coord(
+    coord: &AnmCoord,
+    from_anm: &NuccAnm,
+    from_references: &[NuccStructReference],
+    to_anm: &NuccAnm,
+    to_references: &[NuccStructReference],
+) -> Option<AnmCoord> {
+    let from_clump = from_anm
+        .clumps
+        .get(usize::try_from(coord.clump_index).ok()?)?;
+    let clump_info = &from_references
+        .get(from_clump.clump_index as usize)?
+        .struct_info;
+    let coord_info = &from_references
+        .get(
+            *from_clump
+                .bone_material_indices
+                .get(coord.coord_index as usize)? as usize,
+        )?
+        .struct_info;
+
+    let (clump_index, to_clump) = to_anm.clumps.iter().enumerate().find(|(_, clump)| {
+        to_references
+            .get(clump.clump_index as usize)
+            .is_some_and(|reference| same_chunk(&reference.struct_info, clump_info))
+    })?;
+
+    let coord_index = to_clump.bone_material_indices.iter().position(|index| {
+        to_references
+            .get(*index as usize)
+            .is_some_and(|reference| same_chunk(&reference.struct_info, coord_info))
+    })?;
+
+    Some(AnmCoord {
+        clump_index: clump_index as i16,
+        coord_index: coord_index as u16,
+    })
+}
+
+fn same_chunk(a: &NuccStructInfo, b: &NuccStructInfo) -> bool {
+    a.chunk_name == b.chunk_name && a.chunk_type == b.chunk_type && a.filepath == b.filepath
+}
+
+/// Builds the ANMSTRM entry data of an ANM entry at the given tick.
+fn sample_entry(anm_entry: &AnmEntry, tick: i32, frame_ticks: i32) -> Option<Entry> {
+    let curves = &anm_entry.curves;
+    let vector3 = |index: usize| {
+        curves
+            .get(index)
+            .and_then(|curve| sample_vector3(curve, tick, frame_ticks))
+    };
+    let quaternion = |index: usize| {
+        curves
+            .get(index)
+            .and_then(|curve| sample_quaternion(curve, tick, frame_ticks))
+    };
+    let float = |index: usize| {
+        curves
+            .get(index)
+            .and_then(|curve| sample_float(curve, tick, frame_ticks))
+    };
+    let color = |index: usize| {
+        curves
+            .get(index)
+            .and_then(|curve| sample_color(curve, tick, frame_ticks))
+    };
+
+    let entry_format = anm_entry.entry_format;
+
+    if entry_format == AnmEntryFormat::BONE as u16 {
+        Some(Entry::Bone(AnmStrmEntryBone {
+            location: vector3(0).unwrap_or_default(),
+            rotation: quaternion(1).unwrap_or(IDENTITY_QUATERNION),
+            scale: vector3(2).unwrap_or(ONE_VECTOR3),
+            toggled: float(3).unwrap_or_default(),
+        }))
+    } else if entry_format == AnmEntryFormat::MATERIAL as u16 {
+        Some(Entry::Material(AnmStrmEntryMaterial {
+            ambient_color: (0..16)
+                .map(|index| float(index).unwrap_or_default())
+                .collect(),
+        }))
+    } else if entry_format == AnmEntryFormat::CAMERA as u16 {
+        Some(Entry::Camera(AnmStrmEntryCamera {
+            location: vector3(0).unwrap_or_default(),
+            rotation: quaternion(1).unwrap_or(IDENTITY_QUATERNION),
+            fov: float(2).unwrap_or_default(),
+        }))
+    } else if entry_format == AnmEntryFormat::LIGHTDIRC as u16 {
+        Some(Entry::LightDirc(AnmStrmEntryLightDirc {
+            color: color(0).unwrap_or_default(),
+            intensity: float(1).unwrap_or_default(),
+            direction: quaternion(2).unwrap_or(IDENTITY_QUATERNION),
+        }))
+    } else if entry_format == AnmEntryFormat::LIGHTPOINT as u16 {
+        Some(Entry::LightPoint(AnmStrmEntryLightPoint {
+            color: color(0).unwrap_or_default(),
+            intensity: float(1).unwrap_or_default(),
+            position: vector3(2).unwrap_or_default(),
+            radius: float(3).unwrap_or_default(),
+            falloff: float(4).unwrap_or_default(),
+        }))
+    } else if entry_format == AnmEntryFormat::AMBIENT as u16 {
+        Some(Entry::Ambient(AnmStrmEntryAmbient {
+            color: color(0).unwrap_or_default(),
+            intensity: float(1).unwrap_or_default(),
+        }))
+    } else if entry_format == AnmEntryFormat::MORPHMODEL as u16 {
+        let morph_weight: Vec<f32> = (0..2).filter_map(float).collect();
+
+        Some(Entry::MorphModel(AnmStrmEntryMorphModel {
+            frame_count: morph_weight.len() as u32,
+            morph_weight,
+        }))
+    } else {
+        None
+    }
+}
+
+const IDENTITY_QUATERNION: Vector4 = Vector4 {
+    x: 0.0,
+    y: 0.0,
+    z: 0.0,
+    w: 1.0,
+};
+
+const ONE_VECTOR3: Vector3 = Vector3 {
+    x: 1.0,
+    y: 1.0,
+    z: 1.0,
+};
+
+/// Finds the first ANM matching the predicate and the index of its page.
+pub(crate) fn find_anm(
+    xfbin: &Xfbin,
+    predicate: impl Fn(&NuccAnm) -> bool,
+) -> Result<Option<(usize, &NuccAnm)>, StrmConvError> {
+    for (page_index, page) in xfbin.pages.iter().enumerate() {
+        for nucc_struct in &page.structs {
+            if let NuccChunkType::NuccChunkAnm = nucc_struct.chunk_type() {
+                let anm = nucc_struct.downcast_ref::<NuccAnm>().ok_or_else(|| {
+                    StrmConvError::DowncastFailed {
+                        chunk_type: NuccChunkType::NuccChunkAnm.to_string(),
+                    }
+                })?;
+
+                if predicate(anm) {
+                    return Ok(Some((page_index, anm)));
+                }
+            }
+        }
+    }
+
+    Ok(None)
+}
@@ -0,0 +1,124 @@
+use xfbin::nucc_chunk::nucc_chunk_anm::Curve;
+use xfbin::nucc_chunk::nucc_helper::*;
+
+use crate::math::{quaternion_slerp, vector3_lerp};
+
+/// Number of keyframe ticks in one frame.
+pub const FRAME_TICKS: i32 = 100;
+
+const QUAT_DECOMPRESS: f32 = 1.0 / 0x4000 as f32;
+const RGB_DECOMPRESS: f32 = 1.0 / 255.0;
+
+/// Samples a location or scale curve at the given tick.
+///
+/// `frame_ticks` is the number of ticks in one frame, curves without keyframes store one value
+/// per frame.
+pub fn sample_vector3(curve: &Curve, tick: i32, frame_ticks: i32) -> Option<Vector3> {
+    match curve {
+        Curve::KeyframeVector3(keyframes) => {
+            let keyframes = keyframes
+                .iter()
+                .filter(|keyframe| keyframe.frame >= 0)
+                .map(|keyframe| (keyframe.frame, &keyframe.value))
+                .collect::<Vec<_>>();
+
+            let (from, to, t) = surrounding_keys(&keyframes, tick)?;
+
+            Some(vector3_lerp(from, to, t))
+        }
+        Curve::Vector3(values) => frame_value(values, tick, frame_ticks).cloned(),
+        _ => None,
+    }
+}
+
+/// Samples a float curve at the given tick.
+pub fn sample_float(curve: &Curve, tick: i32, frame_ticks: i32) -> Option<f32> {
+    match curve {
+        Curve::KeyframeFloat(keyframes) => {
+            let keyframes = keyframes
+                .iter()
+                .filter(|keyframe| keyframe.frame >= 0)
+                .map(|keyframe| (keyframe.frame, &keyframe.value))
+                .collect::<Vec<_>>();
+
+            let (from, to, t) = surrounding_keys(&keyframes, tick)?;
+
+            Some(lerp(*from, *to, t))
+        }
+        Curve::Float(values) => frame_value(values, tick, frame_ticks).copied(),
+        _ => None,
+    }
+}
+
+/// Samples a rotation curve at the given tick as an uncompressed quaternion.
+pub fn sample_quaternion(curve: &Curve, tick: i32, frame_ticks: i32) -> Option<Vector4> {
+    match curve {
+        Curve::QuaternionShort(values) => {
+            frame_value(values, tick, frame_ticks).map(decompress_quaternion)
+        }
+        Curve::KeyframeVector4(keyframes) => {
+            let keyframes = keyframes
+                .iter()
+                .filter(|keyframe| keyframe.frame >= 0)
+                .map(|keyframe| (keyframe.frame, &keyframe.value))
+                .collect::<Vec<_>>();
+
+            let (from, to, t) = surrounding_keys(&keyframes, tick)?;
+
+            Some(quaternion_slerp(from, to, t))
+        }
+        _ => None,
+    }
+}
+
+/// Samples a color curve at the given tick with every channel in the 0-1 range.
+pub fn sample_color(curve: &Curve, tick: i32, frame_ticks: i32) -> Option<Vector3> {
+    match curve {
+        Curve::RGB(values) => frame_value(values, tick, frame_ticks).map(|color| Vector3 {
+            x: color.r as f32 * RGB_DECOMPRESS,
+            y: color.g as f32 * RGB_DECOMPRESS,
+            z: color.b as f32 * RGB_DECOMPRESS,
+        }),
+        _ => None,
+    }
+}
+
+/// Converts a compressed quaternion back to floats.
+pub fn decompress_quaternion(quaternion: &QuaternionShort) -> Vector4 {
+    Vector4 {
+        x: quaternion.x as f32 * QUAT_DECOMPRESS,
+        y: quaternion.y as f32 * QUAT_DECOMPRESS,
+        z: quaternion.z as f32 * QUAT_DECOMPRESS,
+        w: quaternion.w as f32 * QUAT_DECOMPRESS,
+    }
+}
+
+/// Returns the value of a curve with one value per frame, holding the last value.
+fn frame_value<T>(values: &[T], tick: i32, frame_ticks: i32) -> Option<&T> {
+    let frame = (tick.max(0) / frame_ticks.max(1)) as usize;
+
+    values.get(frame).or_else(|| values.last())
+}
+
+/// Finds the keys around a tick and how far the tick is between them.
+///
+/// Ticks before the first or after the last key hold that key's value.
+fn surrounding_keys<'a, T>(keyframes: &[(i32, &'a T)], tick: i32) -> Option<(&'a T, &'a T, f32)> {
+    let next = keyframes.iter().position(|(frame, _)| *frame >= tick);
+
+    match next {
+        Some(0) => Some((keyframes[0].1, keyframes[0].1, 0.0)),
+        Some(next) => {
+            let (from_frame, from) = keyframes[next - 1];
+            let (to_frame, to) = keyframes[next];
+            let t = (tick - from_frame) as f32 / (to_frame - from_frame) as f32;
+
+            Some((from, to, t))
+        }
+        None => keyframes.last().map(|(_, value)| (*value, *value, 0.0)),
+    }
+}
+
+fn lerp(from: f32, to: f32, t: f32) -> f32 {
+    from + (to - from) * t
+}
@@ -0,0 +1,175 @@
+use xfbin::nucc::nucc_anm::NuccAnm;
+use xfbin::nucc::{NuccStruct, NuccStructInfo, NuccStructReference};
+use xfbin::{xfbin::XfbinPage, Xfbin};
+
+use crate::error::StrmConvError;
+use crate::remap::{retain_referenced_struct_infos, ClumpRemap};
+use crate::reverse::find_anm;
+
+/// A set of clumps written to its own ANM.
+#[derive(Debug, Clone)]
+pub struct SplitGroup {
+    /// Chunk name of the new ANM, used for the output file name.
+    pub chunk_name: String,
+    /// Chunk names of the clumps that go into the new ANM.
+    pub clump_names: Vec<String>,
+}
+
+/// Options for [`split_anm_xfbin`].
+#[derive(Debug, Clone, Default)]
+pub struct SplitOptions {
+    /// Chunk name of the ANM to split, the first ANM that isn't a DMG page when `None`.
+    pub chunk_name: Option<String>,
+    pub groups: Vec<SplitGroup>,
+    /// Whether the cameras, lights and other entries that don't belong to a clump go into
+    /// every new ANM.
+    pub keep_other_entries: bool,
+}
+
+/// One ANM written by [`split_anm_xfbin`].
+pub struct SplitOutput {
+    /// Chunk name of the new ANM, used for the output file name.
+    pub chunk_name: String,
+    /// The xfbin with the new ANM page.
+    pub xfbin: Xfbin,
+}
+
+/// Splits an ANM into one ANM per group of clumps.
+///
+/// Clumps that aren't in any group are left out of every new ANM.
+pub fn split_anm_xfbin(
+    input: &Xfbin,
+    options: &SplitOptions,
+) -> Result<Vec<SplitOutput>, StrmConvError> {
+    let (page_index, anm) = find_anm(input, |anm| match &options.chunk_name {
+        Some(chunk_name) => anm.struct_info.chunk_name == *chunk_name,
+        None => !anm.struct_info.chunk_name.ends_with("_dmg"),
+    })?
+    .ok_or_else(|| match &options.chunk_name {
+        Some(chunk_name) => StrmConvError::NoStructInfo {
+            chunk_name: chunk_name.clone(),
+        },
+        None => StrmConvError::NoAnmChunk,
+    })?;
+
+    let page = &input.pages[page_index];
+
+    options
+        .groups
+        .iter()
+        .map(|group| {
+            let clump_indices = group
+                .clump_names
+                .iter()
+                .map(|clump_name| {
+                    find_clump(anm, &page.struct_references, clump_name).ok_or_else(|| {
+                        StrmConvError::NoStructInfo {
+                            chunk_name: clump_name.clone(),
+                        }
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let (split_anm, struct_references) = split_anm(
+                anm,
+                &page.struct_references,
+                &clump_indices,
+                options.keep_other_entries,
+            );
+
+            let struct_info = NuccStructInfo {
+                chunk_name: group.chunk_name.clone(),
+                ..anm.struct_info.clone()
+            };
+
+            let mut struct_infos = page.struct_infos.clone();
+            retain_referenced_struct_infos(
+                &mut struct_infos,
+                &page.struct_references,
+                &struct_references,
+            );
+            for info in &mut struct_infos {
+                if *info == anm.struct_info {
+                    *info = struct_info.clone();
+                }
+            }
+
+            let mut split_page = XfbinPage {
+                struct_infos,
+                struct_references,
+                structs: Vec::new(),
+            };
+
+            if options.keep_other_entries {
+                for nucc_struct in &page.structs {
+                    if let Some(nucc_struct) = crate::clone_scene_struct(nucc_struct.as_ref()) {
+                        split_page.structs.push(nucc_struct);
+                    }
+                }
+            }
+            split_page.structs.push(Box::new(NuccAnm {
+                struct_info,
+                ..split_anm
+            }) as Box<dyn NuccStruct>);
+
+            let mut xfbin = Xfbin::default();
+            xfbin.pages.push(split_page);
+
+            Ok(SplitOutput {
+                chunk_name: group.chunk_name.clone(),
+                xfbin,
+            })
+        })
+        .collect()
+}
+
+/// Builds an ANM with only the clumps at `clump_indices` and the struct references it still uses.
+pub fn split_anm(
+    anm: &NuccAnm,
+    struct_references: &[NuccStructReference],
+    clump_indices: &[usize],
+    keep_other_entries: bool,
+) -> (NuccAnm, Vec<NuccStructReference>) {
+    let removed_clumps: Vec<usize> = (0..anm.clumps.len())
+        .filter(|i| !clump_indices.contains(i))
+        .collect();
+
+    let mut remap = ClumpRemap::new(&anm.clumps, struct_references.len(), &removed_clumps);
+    if !keep_other_entries {
+        remap.remove_references(&anm.other_entries_indices);
+    }
+
+    let belongs_to_clump = |clump_index: i16| keep_other_entries || clump_index >= 0;
+
+    let mut coord_parents = remap.remap_coord_parents(&anm.coord_parents);
+    coord_parents.retain(|coord_parent| {
+        belongs_to_clump(coord_parent.parent.clump_index)
+            && belongs_to_clump(coord_parent.child.clump_index)
+    });
+
+    let mut entries = remap.remap_entries(&anm.entries);
+    entries.retain(|entry| belongs_to_clump(entry.coord.clump_index));
+
+    let split_anm = NuccAnm {
+        clumps: remap.remap_clumps(&anm.clumps),
+        other_entries_indices: remap.remap_reference_indices(&anm.other_entries_indices),
+        coord_parents,
+        entries,
+        ..anm.clone()
+    };
+
+    (split_anm, remap.remap_references(struct_references))
+}
+
+/// Finds the index of the clump whose struct reference has this chunk name.
+fn find_clump(
+    anm: &NuccAnm,
+    struct_references: &[NuccStructReference],
+    clump_name: &str,
+) -> Option<usize> {
+    anm.clumps.iter().position(|clump| {
+        struct_references
+            .get(clump.clump_index as usize)
+            .is_some_and(|reference| reference.struct_info.chunk_name == clump_name)
+    })
+}
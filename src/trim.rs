@@ -0,0 +1,133 @@
+use std::fmt;
+use std::str::FromStr;
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::{Reader, Writer};
+
+use crate::error::StrmConvError;
+
+/// A window of frames to convert, both ends included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameRange {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl FrameRange {
+    pub fn contains(&self, frame: u32) -> bool {
+        (self.start..=self.end).contains(&frame)
+    }
+}
+
+impl fmt::Display for FrameRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..={}", self.start, self.end)
+    }
+}
+
+impl FromStr for FrameRange {
+    type Err = String;
+
+    /// Parses `start..end` (end excluded) or `start..=end` (end included).
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let parse = |frame: &str| {
+            frame
+                .trim()
+                .parse::<u32>()
+                .map_err(|_| format!("'{}' is not a frame number", frame.trim()))
+        };
+
+        let (start, end) = match value.split_once("..=") {
+            Some((start, end)) => (parse(start)?, parse(end)?),
+            None => match value.split_once("..") {
+                Some((start, end)) => {
+                    let (start, end) = (parse(start)?, parse(end)?);
+                    if end <= start {
+                        return Err(format!("the range {} has no frames", value));
+                    }
+
+                    (start, end - 1)
+                }
+                None => return Err("expected START..END or START..=END".to_string()),
+            },
+        };
+
+        if end < start {
+            return Err(format!("the range {} has no frames", value));
+        }
+
+        Ok(Self { start, end })
+    }
+}
+
+/// Keeps the frame settings of a frame settings .xml that are inside the range.
+///
+/// Every element with a `frame` attribute outside the range is dropped with its children, the
+/// others get their frame moved so the range starts at frame 0.
+pub fn trim_frame_settings_xml(xml: &str, range: &FrameRange) -> Result<String, StrmConvError> {
+    let mut reader = Reader::from_str(xml);
+    let mut writer = Writer::new(Vec::new());
+
+    // Depth of the dropped element being skipped, 0 when nothing is skipped
+    let mut skip_depth = 0;
+
+    loop {
+        let event = reader.read_event()?;
+
+        if skip_depth > 0 {
+            match event {
+                Event::Start(_) => skip_depth += 1,
+                Event::End(_) => skip_depth -= 1,
+                Event::Eof => break,
+                _ => {}
+            }
+            continue;
+        }
+
+        match event {
+            Event::Start(element) => match rebase_frame(&element, range)? {
+                Some(element) => writer.write_event(Event::Start(element))?,
+                None => skip_depth = 1,
+            },
+            Event::Empty(element) => {
+                if let Some(element) = rebase_frame(&element, range)? {
+                    writer.write_event(Event::Empty(element))?;
+                }
+            }
+            Event::Eof => break,
+            event => writer.write_event(event)?,
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&writer.into_inner()).into_owned())
+}
+
+/// Moves the `frame` attribute of an element to the start of the range, `None` when it's outside.
+fn rebase_frame(
+    element: &BytesStart,
+    range: &FrameRange,
+) -> Result<Option<BytesStart<'static>>, StrmConvError> {
+    let name = String::from_utf8_lossy(element.name().as_ref()).into_owned();
+    let mut rebased = BytesStart::new(name);
+
+    for attribute in element.attributes() {
+        let attribute = attribute.map_err(quick_xml::Error::from)?;
+        let key = String::from_utf8_lossy(attribute.key.as_ref()).into_owned();
+        let value = attribute.unescape_value()?.into_owned();
+
+        if key.eq_ignore_ascii_case("frame") {
+            if let Ok(frame) = value.trim().parse::<u32>() {
+                if !range.contains(frame) {
+                    return Ok(None);
+                }
+
+                rebased.push_attribute((key.as_str(), (frame - range.start).to_string().as_str()));
+                continue;
+            }
+        }
+
+        rebased.push_attribute((key.as_str(), value.as_str()));
+    }
+
+    Ok(Some(rebased))
+}
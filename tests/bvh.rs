@@ -0,0 +1,131 @@
+use strmconv::bvh::{export_bvh, BvhOptions};
+use xfbin::nucc::nucc_anmstrm::NuccAnmStrm;
+use xfbin::nucc::nucc_anmstrmframe::NuccAnmStrmFrame;
+use xfbin::nucc::{NuccStruct, NuccStructInfo, NuccStructReference};
+use xfbin::nucc_chunk::nucc_chunk_anm::{AnmCoord, AnmEntryFormat, CoordParent};
+use xfbin::nucc_chunk::nucc_chunk_anmstrm::AnmStrmClump;
+use xfbin::nucc_chunk::nucc_chunk_anmstrmframe::{AnmStrmEntry, AnmStrmEntryBone, Entry};
+use xfbin::nucc_chunk::nucc_helper::{Vector3, Vector4};
+use xfbin::nucc_chunk::NuccChunkType;
+use xfbin::xfbin::XfbinPage;
+use xfbin::Xfbin;
+
+fn struct_info(chunk_name: &str, chunk_type: NuccChunkType) -> NuccStructInfo {
+    NuccStructInfo {
+        chunk_name: chunk_name.to_string(),
+        chunk_type: chunk_type.to_string(),
+        filepath: "c/1nrt/anm/1nrt.max".to_string(),
+    }
+}
+
+fn reference(chunk_name: &str, chunk_type: NuccChunkType) -> NuccStructReference {
+    NuccStructReference {
+        chunk_name: chunk_name.to_string(),
+        struct_info: struct_info(chunk_name, chunk_type),
+    }
+}
+
+fn coord(coord_index: u16) -> AnmCoord {
+    AnmCoord {
+        clump_index: 0,
+        coord_index,
+    }
+}
+
+fn bone_entry(coord_index: u16, x: f32) -> AnmStrmEntry {
+    AnmStrmEntry {
+        coord: coord(coord_index),
+        entry_format: AnmEntryFormat::BONE as u16,
+        entry_data: Entry::Bone(AnmStrmEntryBone {
+            location: Vector3 { x, y: 0.0, z: 0.0 },
+            rotation: Vector4 {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+                w: 1.0,
+            },
+            scale: Vector3 {
+                x: 1.0,
+                y: 1.0,
+                z: 1.0,
+            },
+            toggled: 0.0,
+        }),
+    }
+}
+
+fn anmstrmframe(frame: u32, entries: Vec<AnmStrmEntry>) -> Box<dyn NuccStruct> {
+    Box::new(NuccAnmStrmFrame {
+        struct_info: struct_info("d01_01", NuccChunkType::NuccChunkAnmStrmFrame),
+        version: 121,
+        frame,
+        entries,
+    })
+}
+
+#[test]
+fn builds_hierarchy_from_coord_parents() {
+    // Coord 2 is the parent of coord 0, coord 1 is a second top level bone
+    let anmstrm = NuccAnmStrm {
+        struct_info: struct_info("d01_01", NuccChunkType::NuccChunkAnmStrm),
+        version: 121,
+        frame_count: 200,
+        frame_size: 100,
+        is_looped: false,
+        clumps: vec![AnmStrmClump {
+            clump_index: 0,
+            bone_material_indices: vec![1, 2, 3],
+            model_indices: Vec::new(),
+        }],
+        other_entry_indices: Vec::new(),
+        coord_parents: vec![CoordParent {
+            parent: coord(2),
+            child: coord(0),
+        }],
+    };
+
+    let page = XfbinPage {
+        struct_infos: vec![anmstrm.struct_info.clone()],
+        struct_references: vec![
+            reference("1nrtbod1", NuccChunkType::NuccChunkClump),
+            reference("spine", NuccChunkType::NuccChunkCoord),
+            reference("prop", NuccChunkType::NuccChunkCoord),
+            reference("hips", NuccChunkType::NuccChunkCoord),
+        ],
+        structs: vec![
+            Box::new(anmstrm),
+            anmstrmframe(
+                0,
+                vec![bone_entry(0, 1.0), bone_entry(1, 0.0), bone_entry(2, 0.0)],
+            ),
+            anmstrmframe(
+                100,
+                vec![bone_entry(0, 2.0), bone_entry(1, 0.0), bone_entry(2, 5.0)],
+            ),
+        ],
+    };
+    let xfbin = Xfbin { pages: vec![page] };
+
+    let outputs = export_bvh(&xfbin, &BvhOptions::default()).unwrap();
+    assert_eq!(outputs.len(), 1);
+    assert_eq!(outputs[0].clump_name, "1nrtbod1");
+
+    let bvh = &outputs[0].bvh;
+    let joints: Vec<&str> = bvh
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with("ROOT") || line.starts_with("JOINT"))
+        .collect();
+    assert_eq!(
+        joints,
+        vec!["ROOT 1nrtbod1", "JOINT prop", "JOINT hips", "JOINT spine"]
+    );
+
+    // Spine is nested in hips, offset by its first frame location
+    assert!(bvh.contains("\n\tJOINT hips\n"), "{}", bvh);
+    assert!(bvh.contains("\n\t\tJOINT spine\n"), "{}", bvh);
+    assert!(bvh.contains("OFFSET 1.000000 0.000000 0.000000"), "{}", bvh);
+
+    assert!(bvh.contains("Frames: 2"), "{}", bvh);
+    assert!(bvh.contains("Frame Time: 0.033333"), "{}", bvh);
+}
@@ -0,0 +1,36 @@
+use strmconv::converter::compress_quaternion;
+use xfbin::nucc_chunk::nucc_helper::{QuaternionShort, Vector4};
+
+fn quaternion(x: f32, y: f32, z: f32, w: f32) -> Vector4 {
+    Vector4 { x, y, z, w }
+}
+
+fn shorts(quaternion: &QuaternionShort) -> [i16; 4] {
+    [quaternion.x, quaternion.y, quaternion.z, quaternion.w]
+}
+
+#[test]
+fn compresses_quaternions() {
+    let mut previous = None;
+
+    // Normalised before compressing
+    let compressed = compress_quaternion(&quaternion(0.0, 0.0, 0.0, 2.0), &mut previous);
+    assert_eq!(shorts(&compressed), [0, 0, 0, 0x4000]);
+    assert_eq!(
+        previous
+            .as_ref()
+            .map(|previous| [previous.x, previous.y, previous.z, previous.w]),
+        Some([0.0, 0.0, 0.0, 1.0])
+    );
+
+    // The same rotation on the other hemisphere is flipped to the previous one
+    let compressed = compress_quaternion(&quaternion(0.0, 0.0, 0.0, -1.0), &mut previous);
+    assert_eq!(shorts(&compressed), [0, 0, 0, 0x4000]);
+
+    let compressed = compress_quaternion(&quaternion(0.5, -0.5, 0.5, 0.5), &mut previous);
+    assert_eq!(shorts(&compressed), [0x2000, -0x2000, 0x2000, 0x2000]);
+
+    // Without a previous rotation the sign is kept
+    let compressed = compress_quaternion(&quaternion(0.0, 0.0, 0.0, -1.0), &mut None);
+    assert_eq!(shorts(&compressed), [0, 0, 0, -0x4000]);
+}
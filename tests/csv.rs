@@ -0,0 +1,129 @@
+use strmconv::csv::{export_bone_csv, CsvOptions};
+use xfbin::nucc::nucc_anmstrm::NuccAnmStrm;
+use xfbin::nucc::nucc_anmstrmframe::NuccAnmStrmFrame;
+use xfbin::nucc::{NuccStruct, NuccStructInfo, NuccStructReference};
+use xfbin::nucc_chunk::nucc_chunk_anm::{AnmCoord, AnmEntryFormat};
+use xfbin::nucc_chunk::nucc_chunk_anmstrm::AnmStrmClump;
+use xfbin::nucc_chunk::nucc_chunk_anmstrmframe::{AnmStrmEntry, AnmStrmEntryBone, Entry};
+use xfbin::nucc_chunk::nucc_helper::{Vector3, Vector4};
+use xfbin::nucc_chunk::NuccChunkType;
+use xfbin::xfbin::XfbinPage;
+use xfbin::Xfbin;
+
+fn struct_info(chunk_name: &str, chunk_type: NuccChunkType) -> NuccStructInfo {
+    NuccStructInfo {
+        chunk_name: chunk_name.to_string(),
+        chunk_type: chunk_type.to_string(),
+        filepath: String::new(),
+    }
+}
+
+fn reference(chunk_name: &str, chunk_type: NuccChunkType) -> NuccStructReference {
+    NuccStructReference {
+        chunk_name: chunk_name.to_string(),
+        struct_info: struct_info(chunk_name, chunk_type),
+    }
+}
+
+fn bone_entry(clump_index: i16, coord_index: u16) -> AnmStrmEntry {
+    AnmStrmEntry {
+        coord: AnmCoord {
+            clump_index,
+            coord_index,
+        },
+        entry_format: AnmEntryFormat::BONE as u16,
+        entry_data: Entry::Bone(AnmStrmEntryBone {
+            location: Vector3 {
+                x: 1.5,
+                y: 0.0,
+                z: 0.0,
+            },
+            rotation: Vector4 {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+                w: 2.0,
+            },
+            scale: Vector3 {
+                x: 1.0,
+                y: 1.0,
+                z: 1.0,
+            },
+            toggled: 0.0,
+        }),
+    }
+}
+
+fn anmstrmframe(frame: u32, entries: Vec<AnmStrmEntry>) -> Box<dyn NuccStruct> {
+    Box::new(NuccAnmStrmFrame {
+        struct_info: struct_info("d01_01", NuccChunkType::NuccChunkAnmStrmFrame),
+        version: 121,
+        frame,
+        entries,
+    })
+}
+
+#[test]
+fn orders_and_escapes_rows() {
+    let clump = |clump_index: u32, bone_material_indices: &[u32]| AnmStrmClump {
+        clump_index,
+        bone_material_indices: bone_material_indices.to_vec(),
+        model_indices: Vec::new(),
+    };
+
+    let anmstrm = NuccAnmStrm {
+        struct_info: struct_info("d01_01", NuccChunkType::NuccChunkAnmStrm),
+        version: 121,
+        frame_count: 200,
+        frame_size: 100,
+        is_looped: false,
+        clumps: vec![clump(0, &[1, 2]), clump(3, &[4])],
+        other_entry_indices: Vec::new(),
+        coord_parents: Vec::new(),
+    };
+
+    let page = XfbinPage {
+        struct_infos: vec![anmstrm.struct_info.clone()],
+        struct_references: vec![
+            reference("1nrtbod1", NuccChunkType::NuccChunkClump),
+            reference("hips", NuccChunkType::NuccChunkCoord),
+            reference("spine, \"upper\"", NuccChunkType::NuccChunkCoord),
+            reference("1sskbod1", NuccChunkType::NuccChunkClump),
+            reference("hips", NuccChunkType::NuccChunkCoord),
+        ],
+        structs: vec![
+            Box::new(anmstrm),
+            // Stored out of order
+            anmstrmframe(
+                100,
+                vec![bone_entry(1, 0), bone_entry(0, 1), bone_entry(0, 0)],
+            ),
+            anmstrmframe(
+                0,
+                vec![bone_entry(1, 0), bone_entry(0, 1), bone_entry(0, 0)],
+            ),
+        ],
+    };
+    let xfbin = Xfbin { pages: vec![page] };
+
+    let csv = export_bone_csv(&xfbin, &CsvOptions::default()).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+
+    assert!(lines[0].starts_with("frame,clump,coord,clump_name,bone_name,"));
+    assert_eq!(lines.len(), 7);
+
+    let keys: Vec<String> = lines[1..]
+        .iter()
+        .map(|line| line.splitn(4, ',').take(3).collect::<Vec<_>>().join(","))
+        .collect();
+    assert_eq!(
+        keys,
+        vec!["0,0,0", "0,0,1", "0,1,0", "1,0,0", "1,0,1", "1,1,0"]
+    );
+
+    // The quotes of the name are doubled and the comma stays inside the field
+    assert_eq!(
+        lines[2],
+        "0,0,1,1nrtbod1,\"spine, \"\"upper\"\"\",1.5,0,0,0,0,0,1,0,0,0,1,1,1,0"
+    );
+}
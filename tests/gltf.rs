@@ -0,0 +1,137 @@
+use serde_json::Value;
+use strmconv::gltf::{export_gltf, GltfOptions};
+use xfbin::nucc::nucc_anm::NuccAnm;
+use xfbin::nucc::{NuccStructInfo, NuccStructReference};
+use xfbin::nucc_chunk::nucc_chunk_anm::{
+    AnmClump, AnmCoord, AnmCurveFormat, AnmEntry, AnmEntryFormat, Curve, CurveHeader,
+};
+use xfbin::nucc_chunk::nucc_helper::{KeyframeVector3, QuaternionShort, Vector3};
+use xfbin::nucc_chunk::NuccChunkType;
+
+fn reference(chunk_name: &str, chunk_type: NuccChunkType) -> NuccStructReference {
+    NuccStructReference {
+        chunk_name: chunk_name.to_string(),
+        struct_info: NuccStructInfo {
+            chunk_name: chunk_name.to_string(),
+            chunk_type: chunk_type.to_string(),
+            filepath: "c/1nrt/anm/1nrt.max".to_string(),
+        },
+    }
+}
+
+fn header(curve_index: u16, curve_format: AnmCurveFormat) -> CurveHeader {
+    CurveHeader {
+        curve_index,
+        curve_format: curve_format as u16,
+        frame_count: 0,
+        curve_size: 0,
+    }
+}
+
+/// An ANM of one clump with one bone that moves along X for three frames.
+fn bone_anm(chunk_name: &str) -> NuccAnm {
+    let location = |frame: i32| KeyframeVector3 {
+        frame: frame * 100,
+        value: Vector3 {
+            x: frame as f32,
+            y: 0.0,
+            z: 0.0,
+        },
+    };
+
+    NuccAnm {
+        struct_info: NuccStructInfo {
+            chunk_name: chunk_name.to_string(),
+            chunk_type: NuccChunkType::NuccChunkAnm.to_string(),
+            filepath: "c/1nrt/anm/1nrt.max".to_string(),
+        },
+        version: 121,
+        frame_count: 200,
+        is_looped: false,
+        clumps: vec![AnmClump {
+            clump_index: 0,
+            bone_material_indices: vec![1],
+            model_indices: Vec::new(),
+        }],
+        other_entries_indices: Vec::new(),
+        unk_entry_indices: Vec::new(),
+        coord_parents: Vec::new(),
+        entries: vec![AnmEntry {
+            coord: AnmCoord {
+                clump_index: 0,
+                coord_index: 0,
+            },
+            entry_format: AnmEntryFormat::BONE as u16,
+            curve_count: 4,
+            curve_headers: vec![
+                header(0, AnmCurveFormat::INT1_FLOAT3),
+                header(1, AnmCurveFormat::SHORT4),
+                header(2, AnmCurveFormat::INT1_FLOAT3),
+                header(3, AnmCurveFormat::FLOAT1ALT),
+            ],
+            curves: vec![
+                Curve::KeyframeVector3(vec![location(0), location(1), location(2)]),
+                Curve::QuaternionShort(vec![QuaternionShort {
+                    x: 0,
+                    y: 0,
+                    z: 0,
+                    w: 0x4000,
+                }]),
+                Curve::KeyframeVector3(Vec::new()),
+                Curve::Float(vec![1.0]),
+            ],
+        }],
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+#[test]
+fn writes_glb_with_an_accessor_per_channel() {
+    let references = [
+        reference("1nrtbod1", NuccChunkType::NuccChunkClump),
+        reference("hips", NuccChunkType::NuccChunkCoord),
+    ];
+    let anm = bone_anm("d01_01");
+    let dmg_anm = bone_anm("d01_01_dmg");
+    let anms = [(&anm, &references[..]), (&dmg_anm, &references[..])];
+
+    let export = export_gltf(&anms, &GltfOptions::default());
+    let glb = export.to_glb();
+
+    assert_eq!(&glb[0..4], b"glTF");
+    assert_eq!(read_u32(&glb, 4), 2);
+    assert_eq!(read_u32(&glb, 8) as usize, glb.len());
+
+    let json_length = read_u32(&glb, 12) as usize;
+    assert_eq!(&glb[16..20], b"JSON");
+    assert_eq!(json_length % 4, 0);
+
+    let bin_offset = 20 + json_length;
+    assert_eq!(&glb[bin_offset + 4..bin_offset + 8], b"BIN\0");
+    assert_eq!(
+        read_u32(&glb, bin_offset) as usize,
+        glb.len() - bin_offset - 8
+    );
+
+    let json: Value = serde_json::from_slice(&glb[20..bin_offset]).unwrap();
+
+    // The clumps of both ANMs share a name but keep their own animations
+    let animations = json["animations"].as_array().unwrap();
+    assert_eq!(animations.len(), 2);
+    for animation in animations {
+        assert_eq!(animation["name"], "1nrtbod1");
+        assert_eq!(animation["channels"].as_array().unwrap().len(), 2);
+    }
+
+    // The times, then a translation and a rotation per ANM, each with a value per frame
+    let accessors = json["accessors"].as_array().unwrap();
+    assert_eq!(accessors.len(), 5);
+    for accessor in accessors {
+        assert_eq!(accessor["count"], 3);
+    }
+    let last_time = accessors[0]["max"][0].as_f64().unwrap();
+    assert!((last_time - 2.0 / 30.0).abs() < 1e-6);
+}
@@ -0,0 +1,116 @@
+use serde_json::{json, Value};
+use strmconv::error::StrmConvError;
+use strmconv::json::import_anm_json;
+use strmconv::sample::FRAME_TICKS;
+
+fn reference(name: &str, chunk_type: &str) -> Value {
+    json!({ "name": name, "chunk_name": name, "chunk_type": chunk_type })
+}
+
+/// A single ANM with one bone, animated by the given curves.
+fn bone_anm(entry_format: u16, curves: Value) -> String {
+    json!({
+        "chunk_name": "d30_10",
+        "frame_count": 100,
+        "clumps": [{
+            "clump": reference("1nrtbod1", "nuccChunkClump"),
+            "bone_materials": [reference("hips", "nuccChunkCoord")],
+        }],
+        "entries": [{
+            "coord": { "clump_index": 0, "coord_index": 0 },
+            "entry_format": entry_format,
+            "curves": curves,
+        }],
+    })
+    .to_string()
+}
+
+fn bone_curves() -> Vec<Value> {
+    let vector3 = json!({
+        "type": "keyframe_vector3",
+        "keys": [{ "frame": 0, "value": [0.0, 0.0, 0.0] }, { "frame": 100, "value": [1.0, 0.0, 0.0] }],
+    });
+
+    vec![
+        vector3.clone(),
+        json!({ "type": "quaternion_short", "keys": [[0, 0, 0, 16384], [0, 0, 0, 16384]] }),
+        vector3,
+        json!({ "type": "float", "keys": [1.0, 1.0] }),
+    ]
+}
+
+fn import_error(json: &str) -> String {
+    match import_anm_json(json, FRAME_TICKS) {
+        Ok(_) => panic!("the JSON was imported"),
+        Err(StrmConvError::InvalidAnmJson { message, .. }) => message,
+        Err(err) => panic!("unexpected error: {}", err),
+    }
+}
+
+#[test]
+fn checks_entry_curves() {
+    let output = import_anm_json(&bone_anm(1, json!(bone_curves())), FRAME_TICKS).unwrap();
+    assert_eq!(output.chunk_name, "d30_10");
+
+    // A bone without its toggle curve
+    let mut curves = bone_curves();
+    curves.pop();
+    let message = import_error(&bone_anm(1, json!(curves)));
+    assert!(message.contains("coord 0:0"), "{}", message);
+    assert!(message.contains("needs 4 curves, not 3"), "{}", message);
+
+    // A rotation stored as floats
+    let mut curves = bone_curves();
+    curves[1] = json!({ "type": "float", "keys": [0.0, 0.0] });
+    let message = import_error(&bone_anm(1, json!(curves)));
+    assert!(message.contains("curve 1"), "{}", message);
+    assert!(
+        message.contains("quaternion_short or keyframe_vector4, not float"),
+        "{}",
+        message
+    );
+
+    // The bone curves under the camera format
+    let message = import_error(&bone_anm(2, json!(bone_curves())));
+    assert!(message.contains("needs 3 curves"), "{}", message);
+
+    let message = import_error(&bone_anm(3, json!(bone_curves())));
+    assert!(message.contains("unknown format 3"), "{}", message);
+}
+
+#[test]
+fn checks_curve_lengths() {
+    // A constant curve only needs its first value
+    let mut curves = bone_curves();
+    curves[3] = json!({ "type": "float", "keys": [1.0] });
+    assert!(import_anm_json(&bone_anm(1, json!(curves)), FRAME_TICKS).is_ok());
+
+    // A per-frame curve missing its last frame
+    let mut curves = bone_curves();
+    curves[3] = json!({ "type": "float", "keys": [1.0, 1.0, 1.0] });
+    let message = import_error(&bone_anm(1, json!(curves)));
+    assert!(message.contains("curve 3"), "{}", message);
+    assert!(
+        message.contains("needs 1 or 2 values, not 3"),
+        "{}",
+        message
+    );
+
+    // Only material keys may be half a frame past the end
+    let mut curves = bone_curves();
+    curves[0] = json!({
+        "type": "keyframe_vector3",
+        "keys": [{ "frame": 0, "value": [0.0, 0.0, 0.0] }, { "frame": 150, "value": [1.0, 0.0, 0.0] }],
+    });
+    let message = import_error(&bone_anm(1, json!(curves)));
+    assert!(message.contains("from 0 to 100"), "{}", message);
+
+    let color = json!({
+        "type": "keyframe_float",
+        "keys": [{ "frame": 0, "value": 0.0 }, { "frame": 150, "value": 1.0 }],
+    });
+    let mut curves = vec![json!({ "type": "float", "keys": [1.0] }); 18];
+    curves[0] = color.clone();
+    curves[1] = color;
+    assert!(import_anm_json(&bone_anm(4, json!(curves)), FRAME_TICKS).is_ok());
+}
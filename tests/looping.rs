@@ -0,0 +1,63 @@
+use strmconv::looping::close_loop;
+use xfbin::nucc_chunk::nucc_chunk_anm::{AnmEntry, AnmEntryFormat, Curve};
+use xfbin::nucc_chunk::nucc_helper::{KeyframeVector3, QuaternionShort, Vector3};
+
+fn key(frame: i32, x: f32) -> KeyframeVector3 {
+    KeyframeVector3 {
+        frame,
+        value: Vector3 { x, y: 0.0, z: 0.0 },
+    }
+}
+
+fn rotation(z: i16, w: i16) -> QuaternionShort {
+    QuaternionShort { x: 0, y: 0, z, w }
+}
+
+#[test]
+fn closes_loop_pops() {
+    let mut anm_entry = AnmEntry {
+        entry_format: AnmEntryFormat::BONE as u16,
+        curves: vec![
+            // Ends a whole unit away from where it starts
+            Curve::KeyframeVector3(vec![key(0, 0.0), key(100, 0.5), key(200, 1.0)]),
+            // Stops before the last frame
+            Curve::KeyframeVector3(vec![key(0, 1.0), key(100, 2.0)]),
+            // Turns almost all the way around, ending on the far side of the sphere
+            Curve::QuaternionShort(vec![
+                rotation(0, 0x4000),
+                rotation(0x2D41, -0x2D41),
+                rotation(0x3FFF, -0x0100),
+            ]),
+            // Off by rounding only
+            Curve::Float(vec![1.0, 1.0, 1.001]),
+        ],
+        ..Default::default()
+    };
+
+    let mut pops = Vec::new();
+    close_loop(&mut anm_entry, 100, &mut pops);
+
+    let pop_curves: Vec<usize> = pops.iter().map(|pop| pop.curve_index).collect();
+    assert_eq!(pop_curves, vec![0, 1, 2]);
+    assert!((pops[0].difference - 1.0).abs() < 1e-6);
+
+    match &anm_entry.curves[0] {
+        Curve::KeyframeVector3(keyframes) => assert_eq!(keyframes[2], key(200, 0.0)),
+        _ => unreachable!(),
+    }
+    match &anm_entry.curves[1] {
+        Curve::KeyframeVector3(keyframes) => {
+            assert_eq!(keyframes, &vec![key(0, 1.0), key(100, 2.0), key(200, 1.0)])
+        }
+        _ => unreachable!(),
+    }
+    match &anm_entry.curves[2] {
+        // The first rotation, on the side of the frame before
+        Curve::QuaternionShort(values) => assert_eq!(values[2], rotation(0, -0x4000)),
+        _ => unreachable!(),
+    }
+    match &anm_entry.curves[3] {
+        Curve::Float(values) => assert_eq!(values, &vec![1.0, 1.0, 1.0]),
+        _ => unreachable!(),
+    }
+}
@@ -0,0 +1,94 @@
+use strmconv::merge::AnmMerger;
+use xfbin::nucc::nucc_anm::NuccAnm;
+use xfbin::nucc::{NuccStructInfo, NuccStructReference};
+use xfbin::nucc_chunk::nucc_chunk_anm::{AnmClump, AnmCoord, AnmEntry, AnmEntryFormat};
+use xfbin::nucc_chunk::NuccChunkType;
+
+fn struct_info(chunk_name: &str, chunk_type: NuccChunkType) -> NuccStructInfo {
+    NuccStructInfo {
+        chunk_name: chunk_name.to_string(),
+        chunk_type: chunk_type.to_string(),
+        filepath: String::new(),
+    }
+}
+
+fn reference(chunk_name: &str, chunk_type: NuccChunkType) -> NuccStructReference {
+    NuccStructReference {
+        chunk_name: chunk_name.to_string(),
+        struct_info: struct_info(chunk_name, chunk_type),
+    }
+}
+
+fn coord(clump_index: i16, coord_index: u16) -> AnmCoord {
+    AnmCoord {
+        clump_index,
+        coord_index,
+    }
+}
+
+/// An ANM of one clump whose bones are the references after the clump, all animated.
+fn bone_anm(chunk_name: &str, bone_count: u16) -> NuccAnm {
+    NuccAnm {
+        struct_info: struct_info(chunk_name, NuccChunkType::NuccChunkAnm),
+        version: 121,
+        frame_count: 100,
+        is_looped: false,
+        clumps: vec![AnmClump {
+            clump_index: 0,
+            bone_material_indices: (1..=bone_count as u32).collect(),
+            model_indices: Vec::new(),
+        }],
+        other_entries_indices: Vec::new(),
+        unk_entry_indices: Vec::new(),
+        coord_parents: Vec::new(),
+        entries: (0..bone_count)
+            .map(|coord_index| AnmEntry {
+                coord: coord(0, coord_index),
+                entry_format: AnmEntryFormat::BONE as u16,
+                ..Default::default()
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn reports_coords_animated_twice() {
+    let first_anm = bone_anm("d01_01", 1);
+    let first_references = [
+        reference("1nrtbod1", NuccChunkType::NuccChunkClump),
+        reference("hips", NuccChunkType::NuccChunkCoord),
+    ];
+
+    // The same clump with its bones in another order
+    let second_anm = bone_anm("d01_02", 2);
+    let second_references = [
+        reference("1nrtbod1", NuccChunkType::NuccChunkClump),
+        reference("spine", NuccChunkType::NuccChunkCoord),
+        reference("hips", NuccChunkType::NuccChunkCoord),
+    ];
+
+    let mut merger = AnmMerger::new(first_anm.struct_info.clone(), &first_anm);
+    merger.merge(0, &first_anm, &first_references);
+    merger.merge(1, &second_anm, &second_references);
+    let (anm, references, conflicts) = merger.finish();
+
+    let names: Vec<&str> = references
+        .iter()
+        .map(|reference| reference.chunk_name.as_str())
+        .collect();
+    assert_eq!(names, vec!["1nrtbod1", "hips", "spine"]);
+
+    let coords: Vec<AnmCoord> = anm
+        .entries
+        .iter()
+        .map(|entry| entry.coord.clone())
+        .collect();
+    assert_eq!(coords, vec![coord(0, 0), coord(0, 1)]);
+
+    // Only the hips of the second ANM are dropped
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].source, 1);
+    assert_eq!(conflicts[0].coord, coord(0, 0));
+    assert_eq!(conflicts[0].entry_format, AnmEntryFormat::BONE as u16);
+    assert_eq!(conflicts[0].chunk_name.as_deref(), Some("hips"));
+}
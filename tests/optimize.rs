@@ -0,0 +1,147 @@
+use strmconv::optimize::{reduce_bone_curves, KeyframeReduction, ReductionReport};
+use xfbin::nucc_chunk::nucc_chunk_anm::{
+    AnmCurveFormat, AnmEntry, AnmEntryFormat, Curve, CurveHeader,
+};
+use xfbin::nucc_chunk::nucc_helper::{KeyframeVector3, QuaternionShort, Vector3};
+
+fn key(frame: i32, x: f32) -> KeyframeVector3 {
+    KeyframeVector3 {
+        frame,
+        value: Vector3 { x, y: 0.0, z: 0.0 },
+    }
+}
+
+fn header(curve_index: u16, curve_format: AnmCurveFormat) -> CurveHeader {
+    CurveHeader {
+        curve_index,
+        curve_format: curve_format as u16,
+        frame_count: 0,
+        curve_size: 0,
+    }
+}
+
+fn key_frames(curve: &Curve) -> Vec<i32> {
+    match curve {
+        Curve::KeyframeVector3(keyframes) => keyframes.iter().map(|key| key.frame).collect(),
+        _ => panic!("not a keyed vector curve"),
+    }
+}
+
+#[test]
+fn reduces_bone_location_keys() {
+    let identity = QuaternionShort {
+        x: 0,
+        y: 0,
+        z: 0,
+        w: 0x4000,
+    };
+
+    let mut anm_entry = AnmEntry {
+        entry_format: AnmEntryFormat::BONE as u16,
+        curve_headers: vec![
+            header(0, AnmCurveFormat::INT1_FLOAT3),
+            header(1, AnmCurveFormat::SHORT4),
+            header(2, AnmCurveFormat::INT1_FLOAT3),
+            header(3, AnmCurveFormat::FLOAT1ALT),
+        ],
+        curves: vec![
+            // Moves in a line until frame 3, then jumps
+            Curve::KeyframeVector3(vec![
+                key(0, 0.0),
+                key(100, 1.0),
+                key(200, 2.005),
+                key(300, 3.0),
+                key(400, 10.0),
+            ]),
+            Curve::QuaternionShort(vec![identity.clone(), identity.clone(), identity]),
+            Curve::KeyframeVector3(vec![key(0, 1.0), key(100, 1.0), key(200, 1.0)]),
+            Curve::Float(vec![1.0, 1.0, 1.0]),
+        ],
+        ..Default::default()
+    };
+
+    let mut report = ReductionReport::default();
+    reduce_bone_curves(
+        &mut anm_entry,
+        &KeyframeReduction::default(),
+        100,
+        &mut report,
+    );
+
+    assert_eq!(key_frames(&anm_entry.curves[0]), vec![0, 300, 400]);
+    assert_eq!(key_frames(&anm_entry.curves[2]), vec![0, 200]);
+
+    // The static rotation became a single key
+    match &anm_entry.curves[1] {
+        Curve::KeyframeVector4(keyframes) => assert_eq!(keyframes.len(), 1),
+        _ => panic!("not a keyed rotation curve"),
+    }
+    assert_eq!(
+        anm_entry.curve_headers[1].curve_format,
+        AnmCurveFormat::INT1_FLOAT4 as u16
+    );
+
+    match &anm_entry.curves[3] {
+        Curve::Float(values) => assert_eq!(values, &vec![1.0]),
+        _ => panic!("not a float curve"),
+    }
+    assert_eq!(
+        anm_entry.curve_headers[3].curve_format,
+        AnmCurveFormat::FLOAT1 as u16
+    );
+
+    assert_eq!(report.keyframes_before, 14);
+    assert_eq!(report.keyframes_after, 7);
+    assert_eq!(report.static_curves, 2);
+}
+
+#[test]
+fn reduces_bone_rotation_keys() {
+    // Turns around Z at a steady speed for 8 frames, then turns back for 4
+    let angles = (0..=8).chain((4..8).rev()).map(|step| step as f32 * 5.0);
+    let rotations = angles
+        .map(|angle: f32| {
+            let half_angle = angle.to_radians() / 2.0;
+
+            QuaternionShort {
+                x: 0,
+                y: 0,
+                z: (half_angle.sin() * 16384.0).round() as i16,
+                w: (half_angle.cos() * 16384.0).round() as i16,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut anm_entry = AnmEntry {
+        entry_format: AnmEntryFormat::BONE as u16,
+        curve_headers: vec![
+            header(0, AnmCurveFormat::INT1_FLOAT3),
+            header(1, AnmCurveFormat::SHORT4),
+        ],
+        curves: vec![
+            Curve::KeyframeVector3(vec![key(0, 0.0)]),
+            Curve::QuaternionShort(rotations),
+        ],
+        ..Default::default()
+    };
+
+    let mut report = ReductionReport::default();
+    reduce_bone_curves(
+        &mut anm_entry,
+        &KeyframeReduction::default(),
+        100,
+        &mut report,
+    );
+
+    match &anm_entry.curves[1] {
+        Curve::KeyframeVector4(keyframes) => assert_eq!(
+            keyframes.iter().map(|key| key.frame).collect::<Vec<_>>(),
+            vec![0, 800, 1200]
+        ),
+        _ => panic!("not a keyed rotation curve"),
+    }
+    assert_eq!(
+        anm_entry.curve_headers[1].curve_format,
+        AnmCurveFormat::INT1_FLOAT4 as u16
+    );
+}
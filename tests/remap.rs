@@ -0,0 +1,87 @@
+use strmconv::remap::ClumpRemap;
+use xfbin::nucc_chunk::nucc_chunk_anm::{AnmClump, AnmCoord, AnmEntry, CoordParent};
+
+fn clump(clump_index: u32, bone_material_indices: &[u32], model_indices: &[u32]) -> AnmClump {
+    AnmClump {
+        clump_index,
+        bone_material_indices: bone_material_indices.to_vec(),
+        model_indices: model_indices.to_vec(),
+    }
+}
+
+fn coord(clump_index: i16, coord_index: u16) -> AnmCoord {
+    AnmCoord {
+        clump_index,
+        coord_index,
+    }
+}
+
+#[test]
+fn removes_several_clumps() {
+    // References 0-2 belong to clump 0, 3-4 to clump 1, 5-7 to clump 2, 8-9 to clump 3,
+    // 10 is a camera and 11 a material shared by clumps 1 and 2
+    let clumps = vec![
+        clump(0, &[1], &[2]),
+        clump(3, &[4, 11], &[]),
+        clump(5, &[6, 11], &[7]),
+        clump(8, &[9], &[]),
+    ];
+
+    let remap = ClumpRemap::new(&clumps, 12, &[0, 2]);
+
+    assert_eq!(remap.clump(0), None);
+    assert_eq!(remap.clump(1), Some(0));
+    assert_eq!(remap.clump(2), None);
+    assert_eq!(remap.clump(3), Some(1));
+
+    assert_eq!(
+        remap.remap_clumps(&clumps),
+        vec![clump(0, &[1, 5], &[]), clump(2, &[3], &[])]
+    );
+
+    let references: Vec<u32> = (0..12).collect();
+    assert_eq!(remap.remap_references(&references), vec![3, 4, 8, 9, 10, 11]);
+    assert_eq!(remap.remap_reference_indices(&[10]), vec![4]);
+
+    let coord_parents = vec![
+        CoordParent {
+            parent: coord(1, 0),
+            child: coord(3, 2),
+        },
+        CoordParent {
+            parent: coord(2, 0),
+            child: coord(3, 0),
+        },
+        CoordParent {
+            parent: coord(3, 1),
+            child: coord(-1, 0),
+        },
+    ];
+    assert_eq!(
+        remap.remap_coord_parents(&coord_parents),
+        vec![
+            CoordParent {
+                parent: coord(0, 0),
+                child: coord(1, 2),
+            },
+            CoordParent {
+                parent: coord(1, 1),
+                child: coord(-1, 0),
+            },
+        ]
+    );
+
+    let entries: Vec<AnmEntry> = [0, 1, 2, 3]
+        .into_iter()
+        .map(|clump_index| AnmEntry {
+            coord: coord(clump_index, 4),
+            ..Default::default()
+        })
+        .collect();
+    let entry_coords: Vec<AnmCoord> = remap
+        .remap_entries(&entries)
+        .into_iter()
+        .map(|entry| entry.coord)
+        .collect();
+    assert_eq!(entry_coords, vec![coord(0, 4), coord(1, 4)]);
+}
@@ -0,0 +1,55 @@
+use strmconv::resample::resample_entry;
+use xfbin::nucc_chunk::nucc_chunk_anm::{AnmEntry, AnmEntryFormat, Curve};
+
+fn resampled_floats(values: &[f32], time_scale: f32) -> Vec<f32> {
+    let mut anm_entry = AnmEntry {
+        curves: vec![Curve::Float(values.to_vec())],
+        ..Default::default()
+    };
+
+    resample_entry(&mut anm_entry, time_scale, 100);
+
+    match &anm_entry.curves[0] {
+        Curve::Float(values) => values.clone(),
+        _ => panic!("the float curve changed its type"),
+    }
+}
+
+#[test]
+fn resamples_per_frame_curves() {
+    // Twice as many frames, the new ones are interpolated
+    assert_eq!(
+        resampled_floats(&[0.0, 10.0, 20.0], 2.0),
+        vec![0.0, 5.0, 10.0, 15.0, 20.0]
+    );
+
+    // Half as many frames
+    assert_eq!(
+        resampled_floats(&[0.0, 10.0, 20.0, 30.0, 40.0], 0.5),
+        vec![0.0, 20.0, 40.0]
+    );
+
+    // A constant curve is kept as it is
+    assert_eq!(resampled_floats(&[7.0], 2.0), vec![7.0]);
+}
+
+#[test]
+fn holds_bone_toggles() {
+    let mut anm_entry = AnmEntry {
+        entry_format: AnmEntryFormat::BONE as u16,
+        curves: vec![
+            Curve::KeyframeVector3(Vec::new()),
+            Curve::QuaternionShort(Vec::new()),
+            Curve::KeyframeVector3(Vec::new()),
+            Curve::Float(vec![1.0, 0.0, 1.0]),
+        ],
+        ..Default::default()
+    };
+
+    resample_entry(&mut anm_entry, 2.0, 100);
+
+    match &anm_entry.curves[3] {
+        Curve::Float(values) => assert_eq!(values, &vec![1.0, 1.0, 0.0, 0.0, 1.0]),
+        _ => panic!("the toggle curve changed its type"),
+    }
+}
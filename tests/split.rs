@@ -0,0 +1,112 @@
+use strmconv::split::split_anm;
+use xfbin::nucc::nucc_anm::NuccAnm;
+use xfbin::nucc::{NuccStructInfo, NuccStructReference};
+use xfbin::nucc_chunk::nucc_chunk_anm::{AnmClump, AnmCoord, AnmEntry, CoordParent};
+use xfbin::nucc_chunk::NuccChunkType;
+
+fn reference(chunk_name: &str, chunk_type: NuccChunkType) -> NuccStructReference {
+    NuccStructReference {
+        chunk_name: chunk_name.to_string(),
+        struct_info: NuccStructInfo {
+            chunk_name: chunk_name.to_string(),
+            chunk_type: chunk_type.to_string(),
+            filepath: String::new(),
+        },
+    }
+}
+
+fn coord(clump_index: i16, coord_index: u16) -> AnmCoord {
+    AnmCoord {
+        clump_index,
+        coord_index,
+    }
+}
+
+fn entry(clump_index: i16, coord_index: u16) -> AnmEntry {
+    AnmEntry {
+        coord: coord(clump_index, coord_index),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn keeps_only_the_selected_clumps() {
+    let struct_references = vec![
+        reference("1nrtbod1", NuccChunkType::NuccChunkClump),
+        reference("1nrt_hips", NuccChunkType::NuccChunkCoord),
+        reference("1sskbod1", NuccChunkType::NuccChunkClump),
+        reference("1ssk_hips", NuccChunkType::NuccChunkCoord),
+        reference("1ssk_spine", NuccChunkType::NuccChunkCoord),
+        reference("1kkgbod1", NuccChunkType::NuccChunkClump),
+        reference("1kkg_hips", NuccChunkType::NuccChunkCoord),
+        reference("camera01", NuccChunkType::NuccChunkCamera),
+    ];
+
+    let clump = |clump_index: u32, bone_material_indices: &[u32]| AnmClump {
+        clump_index,
+        bone_material_indices: bone_material_indices.to_vec(),
+        model_indices: Vec::new(),
+    };
+
+    let anm = NuccAnm {
+        struct_info: NuccStructInfo {
+            chunk_name: "d01_01".to_string(),
+            chunk_type: NuccChunkType::NuccChunkAnm.to_string(),
+            filepath: String::new(),
+        },
+        version: 121,
+        frame_count: 100,
+        is_looped: false,
+        clumps: vec![clump(0, &[1]), clump(2, &[3, 4]), clump(5, &[6])],
+        other_entries_indices: vec![7],
+        unk_entry_indices: Vec::new(),
+        coord_parents: vec![
+            CoordParent {
+                parent: coord(1, 0),
+                child: coord(1, 1),
+            },
+            CoordParent {
+                parent: coord(2, 0),
+                child: coord(-1, 0),
+            },
+        ],
+        entries: vec![
+            entry(0, 0),
+            entry(1, 0),
+            entry(1, 1),
+            entry(2, 0),
+            entry(-1, 0),
+        ],
+    };
+
+    let (split, references) = split_anm(&anm, &struct_references, &[1], false);
+
+    let names: Vec<&str> = references
+        .iter()
+        .map(|reference| reference.chunk_name.as_str())
+        .collect();
+    assert_eq!(names, vec!["1sskbod1", "1ssk_hips", "1ssk_spine"]);
+
+    assert_eq!(split.clumps, vec![clump(0, &[1, 2])]);
+    assert!(split.other_entries_indices.is_empty());
+
+    let coords: Vec<AnmCoord> = split
+        .entries
+        .iter()
+        .map(|entry| entry.coord.clone())
+        .collect();
+    assert_eq!(coords, vec![coord(0, 0), coord(0, 1)]);
+    assert_eq!(
+        split.coord_parents,
+        vec![CoordParent {
+            parent: coord(0, 0),
+            child: coord(0, 1),
+        }]
+    );
+
+    // The camera stays when other entries are kept
+    let (split, references) = split_anm(&anm, &struct_references, &[1], true);
+    assert_eq!(references.len(), 4);
+    assert_eq!(split.other_entries_indices, vec![3]);
+    assert_eq!(split.entries.len(), 3);
+}
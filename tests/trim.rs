@@ -0,0 +1,22 @@
+use strmconv::trim::FrameRange;
+
+fn range(start: u32, end: u32) -> FrameRange {
+    FrameRange { start, end }
+}
+
+#[test]
+fn parses_frame_ranges() {
+    assert_eq!("10..20".parse(), Ok(range(10, 19)));
+    assert_eq!("10..=20".parse(), Ok(range(10, 20)));
+    assert_eq!(" 3 ..= 3 ".parse(), Ok(range(3, 3)));
+    assert_eq!("0..1".parse(), Ok(range(0, 0)));
+
+    // Empty ranges
+    assert!("5..5".parse::<FrameRange>().is_err());
+    assert!("6..=5".parse::<FrameRange>().is_err());
+
+    // Not a range
+    assert!("10".parse::<FrameRange>().is_err());
+    assert!("a..3".parse::<FrameRange>().is_err());
+    assert!("-1..3".parse::<FrameRange>().is_err());
+}